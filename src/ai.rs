@@ -0,0 +1,111 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::ai::board::{Board, SnakeView};
+use crate::ai::components::{AiController, BotSettings};
+use crate::common::components::Position;
+use crate::food::components::Food;
+use crate::snake::components::{SnakeHead, SnakeState, SpeedSettings};
+use crate::snake::SNAKE_TICK;
+use crate::state::GameState;
+
+pub mod board;
+pub mod brain;
+pub mod components;
+
+/// Most bots that can be added from the lobby, so that they fit alongside the player.
+pub const MAX_BOTS: usize = 4;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BotSettings>()
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                0,
+                ai_decide.run_in_state(GameState::Running).before(SnakeState::Movement),
+            )
+            .add_fixed_timestep_system(SNAKE_TICK, 3, ai_prepare.run_in_state(GameState::Running));
+    }
+}
+
+/// Builds a [`Board`] from the given snake heads and the current food, keeping the order of `heads`.
+pub fn build_board<'a>(
+    heads: impl Iterator<Item = (&'a Position, &'a SnakeHead)>,
+    positions: &Query<&Position, Without<SnakeHead>>,
+    foods: &Query<(&Position, &Food)>,
+) -> Board {
+    Board {
+        snakes: heads
+            .map(|(position, head)| SnakeView {
+                head: *position,
+                direction: head.direction,
+                body: head.tail.iter().filter_map(|tail| positions.get(*tail).ok().copied()).collect(),
+            })
+            .collect(),
+        // Bots only go after food that's good for them
+        food: foods.iter().filter(|(_, food)| food.kind.is_beneficial()).map(|(position, _)| *position).collect(),
+    }
+}
+
+/// Shows every bot about to move the board as this tick left it, for the brains that think elsewhere to get
+/// thinking before they're asked to decide on the next tick.
+fn ai_prepare(
+    settings: Res<SpeedSettings>,
+    mut heads: Query<(Entity, &Position, &SnakeHead, Option<&mut AiController>)>,
+    positions: Query<&Position, Without<SnakeHead>>,
+    foods: Query<(&Position, &Food)>,
+) {
+    if !heads.iter().any(|(_, _, head, ai)| ai.is_some() && settings.moves_next_tick(head)) {
+        return;
+    }
+
+    let order: Vec<Entity> = heads.iter().map(|(entity, _, _, _)| entity).collect();
+    let board = build_board(
+        heads.iter().map(|(_, position, head, _)| (position, head)),
+        &positions,
+        &foods,
+    );
+    let now = Instant::now();
+    for (entity, _, head, ai) in heads.iter_mut() {
+        if let Some(mut ai) = ai {
+            if settings.moves_next_tick(head) {
+                let me = order.iter().position(|e| *e == entity).unwrap();
+                ai.brain.prepare(&board, me, now);
+            }
+        }
+    }
+}
+
+/// Lets every bot about to move pick its next direction, in place of `snake_movement_input`.
+fn ai_decide(
+    settings: Res<SpeedSettings>,
+    mut heads: Query<(Entity, &Position, &mut SnakeHead, Option<&mut AiController>)>,
+    positions: Query<&Position, Without<SnakeHead>>,
+    foods: Query<(&Position, &Food)>,
+) {
+    if !heads.iter().any(|(_, _, head, ai)| ai.is_some() && settings.moves_next_tick(head)) {
+        return;
+    }
+
+    let order: Vec<Entity> = heads.iter().map(|(entity, _, _, _)| entity).collect();
+    let board = build_board(
+        heads.iter().map(|(_, position, head, _)| (position, head)),
+        &positions,
+        &foods,
+    );
+    let me = |entity: Entity| order.iter().position(|e| *e == entity).unwrap();
+    for (entity, _, mut head, ai) in heads.iter_mut() {
+        if let Some(mut ai) = ai {
+            if settings.moves_next_tick(&head) {
+                let dir = ai.brain.decide(&board, me(entity));
+                if dir != head.direction.opposite() {
+                    head.input_direction = dir;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::common::components::{Direction, Position};
+
+/// Read-only snapshot of a single snake, as seen by a [`SnakeBrain`](crate::ai::brain::SnakeBrain).
+#[derive(Clone, Debug, Serialize)]
+pub struct SnakeView {
+    pub head: Position,
+    pub direction: Direction,
+    /// Tail segments ordered from the neck to the tip, excluding the head.
+    pub body: Vec<Position>,
+}
+
+/// Read-only snapshot of the game state that brains choose their next move from.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Board {
+    pub snakes: Vec<SnakeView>,
+    pub food: Vec<Position>,
+}
+
+impl Board {
+    /// All cells currently covered by a snake head or tail segment.
+    pub fn occupied(&self) -> HashSet<Position> {
+        self.snakes.iter().flat_map(|snake| std::iter::once(snake.head).chain(snake.body.iter().copied())).collect()
+    }
+
+    /// Directions snake `me` can move in next tick without reversing or running into an occupied cell.
+    pub fn safe_moves(&self, me: usize, occupied: &HashSet<Position>) -> Vec<Direction> {
+        let snake = &self.snakes[me];
+        Direction::ALL
+            .into_iter()
+            .filter(|dir| *dir != snake.direction.opposite() && !occupied.contains(&snake.head.step(*dir)))
+            .collect()
+    }
+
+    /// Breadth-first search from `start` over free cells, returning the first direction taken on the shortest path
+    /// to the nearest food along with its distance.
+    pub fn nearest_food(&self, start: Position, occupied: &HashSet<Position>) -> Option<(Direction, usize)> {
+        let food: HashSet<Position> = self.food.iter().copied().collect();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::new();
+        for dir in Direction::ALL {
+            let next = start.step(dir);
+            if !occupied.contains(&next) && visited.insert(next) {
+                queue.push_back((next, dir, 1));
+            }
+        }
+        while let Some((pos, first, distance)) = queue.pop_front() {
+            if food.contains(&pos) {
+                return Some((first, distance));
+            }
+            for dir in Direction::ALL {
+                let next = pos.step(dir);
+                if !occupied.contains(&next) && visited.insert(next) {
+                    queue.push_back((next, first, distance + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Number of free cells reachable from `start`, including `start` itself.
+    pub fn reachable_area(&self, start: Position, occupied: &HashSet<Position>) -> usize {
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            for dir in Direction::ALL {
+                let next = pos.step(dir);
+                if !occupied.contains(&next) && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        visited.len()
+    }
+}
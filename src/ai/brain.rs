@@ -0,0 +1,172 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::ai::board::Board;
+use crate::common::components::Direction;
+
+/// Strategy that steers a snake in place of keyboard input.
+pub trait SnakeBrain: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Chooses the direction snake `me` (an index into `board.snakes`) should move in on the next tick.
+    fn decide(&mut self, board: &Board, me: usize) -> Direction;
+
+    /// Called at the end of the tick before the brain decides, with the board as that tick left it, so that a
+    /// brain thinking elsewhere can think in the meantime rather than hold up the game when asked to decide.
+    fn prepare(&mut self, _board: &Board, _me: usize, _now: Instant) {}
+
+    /// Whether the brain can no longer play, e.g. a remote bot that went away.
+    fn is_disconnected(&self) -> bool {
+        false
+    }
+}
+
+/// Picks any move that doesn't immediately collide, keeping its current direction when boxed in.
+pub struct RandomSafe {
+    rng: StdRng,
+}
+
+impl RandomSafe {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl SnakeBrain for RandomSafe {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn decide(&mut self, board: &Board, me: usize) -> Direction {
+        let occupied = board.occupied();
+        *board.safe_moves(me, &occupied).choose(&mut self.rng).unwrap_or(&board.snakes[me].direction)
+    }
+}
+
+/// Heads for the nearest reachable food along the shortest path, moving randomly when there is none.
+pub struct Greedy {
+    fallback: RandomSafe,
+}
+
+impl Greedy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            fallback: RandomSafe::new(seed),
+        }
+    }
+}
+
+impl SnakeBrain for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn decide(&mut self, board: &Board, me: usize) -> Direction {
+        let occupied = board.occupied();
+        let snake = &board.snakes[me];
+        match board.nearest_food(snake.head, &occupied) {
+            Some((dir, _)) if dir != snake.direction.opposite() => dir,
+            _ => self.fallback.decide(board, me),
+        }
+    }
+}
+
+/// Prefers the move that leaves the most room to manoeuvre, breaking ties by distance to food.
+pub struct FloodFill {
+    fallback: RandomSafe,
+}
+
+impl FloodFill {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            fallback: RandomSafe::new(seed),
+        }
+    }
+}
+
+impl SnakeBrain for FloodFill {
+    fn name(&self) -> &str {
+        "floodfill"
+    }
+
+    fn decide(&mut self, board: &Board, me: usize) -> Direction {
+        let mut occupied = board.occupied();
+        let snake = &board.snakes[me];
+        let moves = board.safe_moves(me, &occupied);
+        if moves.is_empty() {
+            return self.fallback.decide(board, me);
+        }
+
+        // Our head leaves its cell but the neck takes it, so it stays blocked while scoring each move
+        occupied.insert(snake.head);
+        moves
+            .into_iter()
+            .map(|dir| {
+                let next = snake.head.step(dir);
+                let area = board.reachable_area(next, &occupied);
+                let food_distance = if board.food.contains(&next) {
+                    0
+                } else {
+                    board.nearest_food(next, &occupied).map_or(usize::MAX, |(_, d)| d)
+                };
+                (dir, area, food_distance)
+            })
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)))
+            .map(|(dir, _, _)| dir)
+            .unwrap()
+    }
+}
+
+/// Built-in brains selectable from the lobby or command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Random,
+    Greedy,
+    FloodFill,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Random, Strategy::Greedy, Strategy::FloodFill];
+
+    pub fn build(self, seed: u64) -> Box<dyn SnakeBrain> {
+        match self {
+            Strategy::Random => Box::new(RandomSafe::new(seed)),
+            Strategy::Greedy => Box::new(Greedy::new(seed)),
+            Strategy::FloodFill => Box::new(FloodFill::new(seed)),
+        }
+    }
+
+    /// The strategy after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|s| *s == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::Random => "random",
+            Strategy::Greedy => "greedy",
+            Strategy::FloodFill => "floodfill",
+        })
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown strategy '{}', expected one of random, greedy, floodfill", s))
+    }
+}
@@ -0,0 +1,24 @@
+use bevy::prelude::Component;
+
+use crate::ai::brain::{SnakeBrain, Strategy};
+
+/// Steers a snake with a [`SnakeBrain`] instead of keyboard input.
+#[derive(Component)]
+pub struct AiController {
+    pub brain: Box<dyn SnakeBrain>,
+}
+
+/// Bots to add to the next game, chosen in the lobby or on the command line.
+pub struct BotSettings {
+    pub count: usize,
+    pub strategy: Strategy,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            strategy: Strategy::Greedy,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::view::RenderLayers;
+use iyes_loopless::prelude::*;
+
+use crate::ai::components::AiController;
+use crate::camera::components::{
+    CameraMode, CameraSettings, MainCamera, Minimap, FOLLOW_RATE, MAX_ZOOM, MINIMAP_MARGIN, MINIMAP_SIZE, MIN_ZOOM,
+    OVERLAY_LAYER, ZOOM_STEP,
+};
+use crate::common::components::ArenaLayout;
+use crate::mode::components::Player;
+use crate::state::GameState;
+
+pub mod components;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraSettings>()
+            .add_startup_system(setup_cameras)
+            .add_enter_system(GameState::MainMenu, reset_cameras)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_not_in_state(GameState::MainMenu)
+                    .run_if(|settings: Res<CameraSettings>| settings.mode == CameraMode::Follow)
+                    .with_system(zoom)
+                    .with_system(follow_player)
+                    .with_system(place_minimap)
+                    .into(),
+            );
+    }
+}
+
+fn setup_cameras(mut commands: Commands) {
+    commands
+        .spawn_bundle(Camera2dBundle::default())
+        .insert(RenderLayers::default().with(OVERLAY_LAYER))
+        .insert(MainCamera);
+    commands
+        .spawn_bundle(Camera2dBundle {
+            camera: Camera {
+                // Drawn over the main camera, and only once there's a snake to follow
+                priority: 1,
+                is_active: false,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::rgb(0.02, 0.02, 0.02)),
+            },
+            ..default()
+        })
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(Minimap);
+}
+
+/// Looks at the whole arena again and hides the minimap, ready for the next game.
+fn reset_cameras(
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut minimaps: Query<&mut Camera, With<Minimap>>,
+) {
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+        projection.scale = 1.0;
+    }
+    for mut camera in minimaps.iter_mut() {
+        camera.is_active = false;
+    }
+}
+
+fn zoom(
+    keys: Res<Input<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut settings: ResMut<CameraSettings>,
+    mut cameras: Query<&mut OrthographicProjection, With<MainCamera>>,
+) {
+    let mut notches: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Touchpads scroll in pixels, a few dozen of which make a notch
+            MouseScrollUnit::Pixel => event.y / 40.0,
+        })
+        .sum();
+    if keys.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        notches += 1.0;
+    }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        notches -= 1.0;
+    }
+    if notches != 0.0 {
+        settings.zoom = (settings.zoom * ZOOM_STEP.powf(notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+    for mut projection in cameras.iter_mut() {
+        projection.scale = 1.0 / settings.zoom;
+    }
+}
+
+/// Glides the camera after the local player's snake. Across the edges of the arena it takes the short way
+/// round like the snake does, rather than sweeping back over the whole board.
+fn follow_player(
+    time: Res<Time>,
+    layout: Res<ArenaLayout>,
+    // Only snake heads belong to a player
+    heads: Query<(&Player, &Transform), Without<AiController>>,
+    mut cameras: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
+) {
+    // Stays where it is while the player is waiting to respawn
+    let target = match heads.iter().find(|(player, _)| **player == Player(0)) {
+        Some((_, head)) => head.translation.truncate(),
+        None => return,
+    };
+    let size = layout.size();
+    let center = layout.center();
+    let catch_up = 1.0 - (-FOLLOW_RATE * time.delta_seconds()).exp();
+    for mut transform in cameras.iter_mut() {
+        let camera = transform.translation.truncate();
+        let delta = wrap(target - camera, Vec2::ZERO, size);
+        let camera = wrap(camera + delta * catch_up, center, size);
+        transform.translation = camera.extend(transform.translation.z);
+    }
+}
+
+/// `point` moved by whole arena sizes until it's within half an arena of `center`.
+fn wrap(point: Vec2, center: Vec2, size: Vec2) -> Vec2 {
+    if size.min_element() <= 0.0 {
+        return point;
+    }
+    let offset = point - center + size / 2.0;
+    center - size / 2.0 + Vec2::new(offset.x.rem_euclid(size.x), offset.y.rem_euclid(size.y))
+}
+
+/// Keeps the minimap in the top right corner of the window, showing the whole arena.
+fn place_minimap(
+    windows: Res<Windows>,
+    layout: Res<ArenaLayout>,
+    mut minimaps: Query<(&mut Camera, &mut Transform, &mut OrthographicProjection), With<Minimap>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let scale_factor = window.scale_factor() as f32;
+    let side = (MINIMAP_SIZE * scale_factor) as u32;
+    let margin = (MINIMAP_MARGIN * scale_factor) as u32;
+    // A viewport reaching outside the window can't be drawn
+    let fits = window.physical_width() >= side + 2 * margin && window.physical_height() >= side + 2 * margin;
+    for (mut camera, mut transform, mut projection) in minimaps.iter_mut() {
+        camera.is_active = fits && layout.tile > 0.0;
+        if !camera.is_active {
+            continue;
+        }
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(window.physical_width() - side - margin, margin),
+            physical_size: UVec2::splat(side),
+            ..default()
+        });
+        transform.translation = layout.center().extend(transform.translation.z);
+        projection.scale = layout.size().max_element() / MINIMAP_SIZE;
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::Component;
+use bevy::render::view::Layer;
+
+/// How the camera looks at the arena.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// The whole arena at once, which is how the game has always been played
+    #[default]
+    Fixed,
+    /// Zoomed in on the local player's snake, with a minimap of the whole arena, for maps too big to fit
+    Follow,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 2] = [CameraMode::Fixed, CameraMode::Follow];
+}
+
+impl fmt::Display for CameraMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CameraMode::Fixed => "fixed",
+            CameraMode::Follow => "follow",
+        })
+    }
+}
+
+impl FromStr for CameraMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown camera '{}', expected one of fixed, follow", s))
+    }
+}
+
+pub struct CameraSettings {
+    pub mode: CameraMode,
+    /// How many times bigger than the whole arena view the board is drawn when following, changed with the
+    /// mouse wheel or the + and - keys
+    pub zoom: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::Fixed,
+            zoom: 3.0,
+        }
+    }
+}
+
+pub const MIN_ZOOM: f32 = 1.0;
+pub const MAX_ZOOM: f32 = 8.0;
+/// Zoom factor of one notch of the mouse wheel or press of a key
+pub const ZOOM_STEP: f32 = 1.15;
+/// How quickly the camera catches up with the snake it follows, the fraction of the way left that's covered
+/// being `1 - e^(-rate * seconds)`
+pub const FOLLOW_RATE: f32 = 6.0;
+/// Side of the minimap, in logical pixels
+pub const MINIMAP_SIZE: f32 = 200.0;
+/// Gap between the minimap and the corner of the window
+pub const MINIMAP_MARGIN: f32 = 20.0;
+/// Render layer for things drawn over the board that would only clutter the minimap, like name tags
+pub const OVERLAY_LAYER: Layer = 1;
+
+/// The camera the game is played through.
+#[derive(Component)]
+pub struct MainCamera;
+
+/// Camera drawing the whole arena into a corner of the window while following a snake.
+#[derive(Component)]
+pub struct Minimap;
@@ -0,0 +1,114 @@
+use std::net::SocketAddr;
+
+use bevy::prelude::Color;
+use clap::{Parser, Subcommand};
+
+use crate::ai::brain::Strategy;
+use crate::camera::components::CameraMode;
+use crate::common::conditioner::NetworkPreset;
+use crate::mode::components::GameMode;
+use crate::player::components::Skin;
+use crate::player::parse_color;
+use crate::theme::components::Theme;
+use crate::tournament::TournamentArgs;
+
+/// Command line options for the game.
+#[derive(Parser, Debug)]
+#[command(name = "snakegame", about = "Multiplayer snake game")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Name shown above your snake and on the scoreboard, instead of the one in the settings
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Color of your snake in hex, e.g. #33cc4d, instead of the one in the settings
+    #[arg(long, value_parser = parse_color)]
+    pub color: Option<Color>,
+
+    /// Pattern along your snake's tail: solid, stripes or gradient
+    #[arg(long, default_value_t = Skin::Solid)]
+    pub skin: Skin,
+
+    /// How the board is drawn: atlas for sprites, or flat for plain squares
+    #[arg(long, default_value_t = Theme::Atlas)]
+    pub theme: Theme,
+
+    /// How the camera looks at the arena: fixed on all of it, or follow your snake with a minimap
+    #[arg(long, default_value_t = CameraMode::Fixed)]
+    pub camera: CameraMode,
+
+    /// How far the camera zooms in on your snake when following it, from 1 to 8
+    #[arg(long, default_value_t = 3.0)]
+    pub zoom: f32,
+
+    /// Seed for the game's random number generator, and for which packets the simulated network conditions
+    /// drop or delay, to replay a game
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Number of AI bot snakes added to a new game
+    #[arg(long, default_value_t = 0)]
+    pub bots: usize,
+
+    /// Strategy the AI bot snakes play with: random, greedy or floodfill
+    #[arg(long, default_value_t = Strategy::Greedy)]
+    pub bot_strategy: Strategy,
+
+    /// Address to accept external bot connections on, e.g. 127.0.0.1:5050
+    #[arg(long)]
+    pub bot_api: Option<SocketAddr>,
+
+    /// How long an external bot has to reply to each tick, at most until its snake moves, before it continues
+    /// straight on
+    #[arg(long, default_value_t = 50)]
+    pub bot_deadline_ms: u64,
+
+    /// Game mode: classic, timed, last-standing, team or free-for-all
+    #[arg(long, default_value_t = GameMode::Classic)]
+    pub mode: GameMode,
+
+    /// Length of a timed match
+    #[arg(long, default_value_t = 3)]
+    pub match_minutes: u64,
+
+    /// Rounds in a last snake standing match
+    #[arg(long, default_value_t = 3)]
+    pub rounds: u32,
+
+    /// Teams in a team match
+    #[arg(long, default_value_t = 2)]
+    pub teams: usize,
+
+    /// Lets teammates collide with each other in a team match
+    #[arg(long)]
+    pub friendly_fire: bool,
+
+    /// Delay before a dead snake respawns in free-for-all
+    #[arg(long, default_value_t = 3.0)]
+    pub respawn_seconds: f32,
+
+    /// How long a respawned snake can't die for
+    #[arg(long, default_value_t = 3.0)]
+    pub invulnerable_seconds: f32,
+
+    /// Unidirectional streams the other end of a connection may have open at once
+    #[arg(long, default_value_t = 8)]
+    pub max_uni_streams: u32,
+
+    /// Bidirectional streams the other end of a connection may have open at once
+    #[arg(long, default_value_t = 1)]
+    pub max_bidi_streams: u32,
+
+    /// Network conditions to simulate on every connection, for testing: perfect, lan, broadband, mobile or
+    /// congested
+    #[arg(long, default_value_t = NetworkPreset::Perfect)]
+    pub network: NetworkPreset,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Runs headless bot-vs-bot matches and prints a leaderboard
+    Tournament(TournamentArgs),
+}
@@ -0,0 +1,352 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use iyes_loopless::prelude::*;
+
+use crate::client::client::{self, ConnectionStatus};
+use crate::common::conditioner::LinkConditions;
+use crate::common::discovery::{self, DiscoveryConfig, ServerInfo};
+use crate::common::protocol::{DisconnectReason, NetMessage, StateUpdate};
+use crate::common::snapshot::SnapshotHistory;
+use crate::common::transport::{Link, Received, StreamLimits};
+use crate::config::UserConfig;
+use crate::mode::components::{MatchState, ServerClock};
+use crate::net::components::{CloseConnections, NetMessageReceived, Peer, Recipient, SendState, StateReceived};
+use crate::net::NetRuntime;
+use crate::player::color_hex;
+use crate::player::components::Identity;
+use crate::state::{GameState, MenuState};
+
+/// Servers kept in the recently joined list, most recent first.
+pub const MAX_RECENT_SERVERS: usize = 5;
+/// How often the servers on the multiplayer screen are pinged.
+pub const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// LAN servers that haven't announced themselves for this long are taken off the list.
+pub const LAN_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long servers get to answer a discovery query.
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct LanServer {
+    pub addr: SocketAddr,
+    pub info: ServerInfo,
+    pub last_seen: Instant,
+}
+
+/// Servers offered on the multiplayer screen, and how joining one of them is going.
+pub struct ServerBrowser {
+    /// Servers announcing themselves on the LAN, in the order they were found
+    pub lan: Vec<LanServer>,
+    /// Round trip time to every server pinged, by the address it was pinged on, or `None` if it didn't answer
+    pub pings: HashMap<String, Option<Duration>>,
+    /// Last news of the connection, for the status line
+    pub status: Option<String>,
+    /// Link to the server joined, while connected
+    pub link: Option<Link>,
+    /// Streams the server joined may open
+    pub limits: StreamLimits,
+    /// Network conditions to simulate on the connection to the server joined
+    pub conditions: LinkConditions,
+    /// Newest snapshots of the server joined's game, which it sends changes to
+    pub snapshots: SnapshotHistory,
+    found: Sender<(SocketAddr, ServerInfo)>,
+    announcements: Receiver<(SocketAddr, ServerInfo)>,
+    ping_sender: Sender<(String, Option<Duration>)>,
+    ping_results: Receiver<(String, Option<Duration>)>,
+    /// News of the server being joined and what it sends. Every join gets new channels, so nothing from a
+    /// server left for another one can come through, and the task joining it stops once it finds nobody listening.
+    statuses: Receiver<ConnectionStatus>,
+    received: Receiver<Received>,
+}
+
+impl FromWorld for ServerBrowser {
+    fn from_world(world: &mut World) -> Self {
+        let (found, announcements) = unbounded();
+        let (ping_sender, ping_results) = unbounded();
+        let listening = found.clone();
+        world.resource::<NetRuntime>().0.spawn(async move {
+            if let Err(e) = discovery::listen(listening, DiscoveryConfig::default()).await {
+                println!("[discovery] can't listen for LAN servers: {}", e);
+            }
+        });
+        Self {
+            lan: vec![],
+            pings: HashMap::new(),
+            status: None,
+            link: None,
+            limits: *world.resource::<StreamLimits>(),
+            conditions: *world.resource::<LinkConditions>(),
+            snapshots: SnapshotHistory::default(),
+            found,
+            announcements,
+            ping_sender,
+            ping_results,
+            statuses: unbounded().1,
+            received: unbounded().1,
+        }
+    }
+}
+
+impl ServerBrowser {
+    /// Starts joining the server at `host` as `identity`, leaving the one joined before if any, and puts it at the
+    /// top of the recently joined servers.
+    pub fn join(&mut self, host: &str, identity: &Identity, runtime: &NetRuntime, config: &mut UserConfig) {
+        let host = host.trim().to_string();
+        if host.is_empty() {
+            return;
+        }
+        self.leave();
+        config.recent_servers.retain(|recent| *recent != host);
+        config.recent_servers.insert(0, host.clone());
+        config.recent_servers.truncate(MAX_RECENT_SERVERS);
+        if let Err(e) = config.save() {
+            warn!("Couldn't save recent servers: {}", e);
+        }
+        let (status, statuses) = unbounded();
+        let (received_sender, received) = unbounded();
+        self.statuses = statuses;
+        self.received = received;
+        runtime.0.spawn(client::join(
+            host,
+            identity.name.clone(),
+            color_hex(identity.color),
+            self.limits,
+            self.conditions,
+            status,
+            received_sender,
+        ));
+    }
+
+    /// Leaves the server joined, if any, or stops joining it.
+    pub fn leave(&mut self) {
+        self.snapshots.clear();
+        if let Some(link) = self.link.take() {
+            DisconnectReason::Left.close(link.connection());
+        }
+        // Dropping the channels tells the task joining the server to stop, however far it got
+        self.statuses = unbounded().1;
+        self.received = unbounded().1;
+    }
+
+    /// Asks the servers on the LAN about themselves rather than waiting for them to announce themselves.
+    fn query_lan(&self, runtime: &NetRuntime) {
+        let found = self.found.clone();
+        runtime.0.spawn(async move {
+            match discovery::discover(&DiscoveryConfig::default(), DISCOVERY_TIMEOUT).await {
+                Ok(servers) => servers.into_iter().for_each(|server| {
+                    let _ = found.send(server);
+                }),
+                Err(e) => println!("[discovery] can't query LAN servers: {}", e),
+            }
+        });
+    }
+
+    fn ping(&self, host: String, runtime: &NetRuntime) {
+        let results = self.ping_sender.clone();
+        runtime.0.spawn(async move {
+            let rtt = client::ping(&host).await.ok();
+            let _ = results.send((host, rtt));
+        });
+    }
+}
+
+pub struct BrowserPlugin;
+
+impl Plugin for BrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServerBrowser>()
+            .add_system(receive_announcements)
+            .add_system(receive_pings)
+            .add_system(receive_connection_status)
+            .add_system(receive_from_server)
+            .add_system(show_server_news.after(receive_from_server))
+            .add_system(receive_snapshots.after(receive_from_server))
+            .add_system(sync_server_clock.after(receive_from_server))
+            .add_system_to_stage(CoreStage::Last, leave_on_exit.label(CloseConnections))
+            .add_system(ping_servers.run_in_state(GameState::MainMenu).run_in_state(MenuState::Multiplayer));
+    }
+}
+
+fn receive_announcements(mut browser: ResMut<ServerBrowser>) {
+    let now = Instant::now();
+    let found: Vec<(SocketAddr, ServerInfo)> = browser.announcements.try_iter().collect();
+    for (addr, info) in found {
+        match browser.lan.iter_mut().find(|server| server.addr == addr) {
+            Some(server) => {
+                server.info = info;
+                server.last_seen = now;
+            }
+            None => browser.lan.push(LanServer {
+                addr,
+                info,
+                last_seen: now,
+            }),
+        }
+    }
+    if browser.lan.iter().any(|server| now - server.last_seen > LAN_TIMEOUT) {
+        browser.lan.retain(|server| now - server.last_seen <= LAN_TIMEOUT);
+    }
+}
+
+/// Pings every server on the multiplayer screen as soon as it's listed, and again every [`PING_INTERVAL`], when
+/// the LAN is queried for servers too.
+fn ping_servers(
+    runtime: Res<NetRuntime>,
+    config: Res<UserConfig>,
+    browser: Res<ServerBrowser>,
+    mut last_queried: Local<Option<Instant>>,
+    mut last_pinged: Local<HashMap<String, Instant>>,
+) {
+    let now = Instant::now();
+    if last_queried.is_none_or(|last| now - last >= PING_INTERVAL) {
+        *last_queried = Some(now);
+        browser.query_lan(&runtime);
+    }
+    let lan = browser.lan.iter().map(|server| server.addr.to_string());
+    for host in config.recent_servers.iter().cloned().chain(lan) {
+        let due = last_pinged.get(&host).is_none_or(|last| now - *last >= PING_INTERVAL);
+        if due {
+            last_pinged.insert(host.clone(), now);
+            browser.ping(host, &runtime);
+        }
+    }
+}
+
+fn receive_pings(mut browser: ResMut<ServerBrowser>) {
+    let results: Vec<(String, Option<Duration>)> = browser.ping_results.try_iter().collect();
+    for (host, rtt) in results {
+        browser.pings.insert(host, rtt);
+    }
+}
+
+fn receive_connection_status(mut browser: ResMut<ServerBrowser>) {
+    let statuses: Vec<ConnectionStatus> = browser.statuses.try_iter().collect();
+    for status in statuses {
+        info!("{:?}", status);
+        if let ConnectionStatus::Reconnecting { .. } | ConnectionStatus::Disconnected(..) = status {
+            browser.link = None;
+        }
+        browser.status = Some(match status {
+            ConnectionStatus::Connecting(host) => format!("Connecting to {}...", host),
+            ConnectionStatus::Connected {
+                addr,
+                slot,
+                name,
+                resumed,
+                link,
+            } => {
+                browser.link = Some(link);
+                let joined = if resumed { "Rejoined" } else { "Connected to" };
+                format!("{} {} as {} (player {})", joined, addr, name, slot)
+            }
+            ConnectionStatus::Reconnecting { addr, attempt, reason } => {
+                format!("Lost {} ({}), reconnecting, attempt {}...", addr, reason, attempt)
+            }
+            ConnectionStatus::Disconnected(addr, reason) => format!("Disconnected from {}: {}", addr, reason),
+            ConnectionStatus::Failed(host, e) => format!("Couldn't connect to {}: {}", host, e),
+        });
+    }
+}
+
+/// Hands what the server joined sends to the game as events.
+fn receive_from_server(
+    browser: Res<ServerBrowser>,
+    mut messages: EventWriter<NetMessageReceived>,
+    mut states: EventWriter<StateReceived>,
+) {
+    for received in browser.received.try_iter() {
+        match received {
+            Received::Message(message) => messages.send(NetMessageReceived {
+                from: Peer::Server,
+                message,
+            }),
+            Received::State(state) => states.send(StateReceived {
+                from: Peer::Server,
+                state,
+            }),
+        }
+    }
+}
+
+/// Puts what happens on the server joined on the status line.
+fn show_server_news(mut received: EventReader<NetMessageReceived>, mut browser: ResMut<ServerBrowser>) {
+    for event in received.iter().filter(|event| event.from == Peer::Server) {
+        browser.status = Some(match &event.message {
+            // Not news, see `sync_server_clock`
+            NetMessage::Phase { .. } => continue,
+            NetMessage::Notice { text } => text.clone(),
+            NetMessage::PlayerJoined { name, .. } => format!("{} joined", name),
+            NetMessage::PlayerLeft { name, reason, .. } => format!("{} left: {}", name, reason),
+            NetMessage::Chat { from, text } => format!("{}: {}", from, text),
+            NetMessage::Died { name, .. } => format!("{} died", name),
+            NetMessage::MatchResult { winner, .. } => {
+                format!("Match over, winner: {}", winner.as_deref().unwrap_or("draw"))
+            }
+        });
+    }
+}
+
+/// Keeps the clock in step with the server joined's, and its countdowns and intermissions ending when the
+/// server's do.
+fn sync_server_clock(
+    mut received: EventReader<NetMessageReceived>,
+    browser: Res<ServerBrowser>,
+    time: Res<Time>,
+    mut clock: ResMut<ServerClock>,
+    mut state: ResMut<MatchState>,
+) {
+    let link = match &browser.link {
+        Some(link) => link,
+        None => {
+            // Back on this game's own clock
+            if clock.phase_ends_at.is_some() {
+                *clock = ServerClock::default();
+            }
+            return;
+        }
+    };
+    for event in received.iter().filter(|event| event.from == Peer::Server) {
+        if let NetMessage::Phase { server_time, ends_at } = event.message {
+            // The server's clock has moved on by about half a round trip since it was read
+            let server_now = server_time + link.connection().rtt().as_secs_f64() / 2.0;
+            clock.offset = server_now - time.seconds_since_startup();
+            clock.phase_ends_at = Some(ends_at);
+            state.phase_ends_at = ends_at;
+        }
+    }
+}
+
+/// Rebuilds the server joined's snapshots from the changes it sends, acknowledging each one so the next
+/// changes can be to it.
+fn receive_snapshots(
+    mut states: EventReader<StateReceived>,
+    mut browser: ResMut<ServerBrowser>,
+    mut outgoing: EventWriter<SendState>,
+) {
+    for received in states.iter().filter(|received| received.from == Peer::Server) {
+        let snapshot = match &received.state {
+            StateUpdate::Snapshot(snapshot) => snapshot.clone(),
+            // Changes to a snapshot that's been forgotten wait for the keyframe the server falls back on
+            StateUpdate::Delta(delta) => match browser.snapshots.get(delta.base).and_then(|base| base.apply(delta)) {
+                Some(snapshot) => snapshot,
+                None => continue,
+            },
+            _ => continue,
+        };
+        outgoing.send(SendState {
+            to: Recipient::Server,
+            state: StateUpdate::Ack { tick: snapshot.tick },
+        });
+        browser.snapshots.push(snapshot);
+    }
+}
+
+/// Tells the server joined that the game is leaving when it closes.
+fn leave_on_exit(mut exits: EventReader<AppExit>, mut browser: ResMut<ServerBrowser>) {
+    if exits.iter().last().is_some() {
+        browser.leave();
+    }
+}
@@ -1,5 +1,48 @@
-use quinn::Endpoint;
+use std::error::Error;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use quinn::{Connection, Endpoint};
+use tokio::net::lookup_host;
+
+use crate::common::conditioner::LinkConditions;
+use crate::common::discovery::PROTOCOL_VERSION;
+use crate::common::protocol::{DisconnectReason, Hello, Welcome, MAX_HANDSHAKE_BYTES, RECONNECT_GRACE};
 use crate::common::quinn_helpers::make_client_endpoint;
+use crate::common::transport::{Link, Received, StreamLimits};
+use crate::server::server::SERVER_PORT;
+
+/// Longest a server gets to answer a ping.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time between attempts to get back to a server the connection to was lost.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Progress of joining a server, reported by [`join`].
+#[derive(Debug)]
+pub enum ConnectionStatus {
+    Connecting(String),
+    Connected {
+        addr: SocketAddr,
+        slot: usize,
+        /// Name the server settled on, see `Welcome`
+        name: String,
+        /// Whether this is the slot held before the connection was lost
+        resumed: bool,
+        /// For the game to talk to the server, and leave it, with
+        link: Link,
+    },
+    /// The connection was lost, and is being made again to get back to the same slot
+    Reconnecting {
+        addr: SocketAddr,
+        attempt: u32,
+        reason: DisconnectReason,
+    },
+    Disconnected(SocketAddr, DisconnectReason),
+    /// Never got in, with why not
+    Failed(String, String),
+}
 
 // pub fn client_main() {
 //     let code = {
@@ -13,22 +56,153 @@ use crate::common::quinn_helpers::make_client_endpoint;
 //     ::std::process::exit(code);
 // }
 
-//#[tokio::main]
-pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let server_addr = "127.0.0.1:5000".parse().unwrap();
-    let client_addr = "127.0.0.1:5001".parse().unwrap();
-    // Bind this endpoint to a UDP socket on the given client address.
-    let mut endpoint = make_client_endpoint(client_addr, &[])?;
+/// Looks up a server given as `host:port`, or just `host` for the default port.
+pub async fn resolve(host: &str) -> io::Result<SocketAddr> {
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:{}", host, SERVER_PORT) };
+    let found = lookup_host(host.as_str()).await?.next();
+    found.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no address found for {}", host)))
+}
 
-    // Connect to the server passing in the server name which is supposed to be in the server certificate.
-    let connection = endpoint.connect(server_addr, "localhost")?.await?;
-    println!("[client] connected: addr={}", connection.remote_address());
+/// Joins the server at `host` as `name` in `color` and stays until either end closes the connection, reporting
+/// how it goes to `status` and handing what the server sends to `received`. If the connection drops, it's made
+/// again until the server gives up on keeping the slot, or nobody is listening to `status` any more.
+pub async fn join(
+    host: String,
+    name: String,
+    color: String,
+    limits: StreamLimits,
+    conditions: LinkConditions,
+    status: Sender<ConnectionStatus>,
+    received: Sender<Received>,
+) {
+    let _ = status.send(ConnectionStatus::Connecting(host.clone()));
+    let connected = resolve(&host).await.map_err(|e| e.to_string()).and_then(|server_addr| {
+        // Bind this endpoint to any free UDP port, so several clients can run on one machine
+        let endpoint = make_client_endpoint((Ipv4Addr::UNSPECIFIED, 0).into(), &[], limits, conditions)
+            .map_err(|e| e.to_string())?;
+        Ok((server_addr, endpoint))
+    });
+    let (server_addr, endpoint) = match connected {
+        Ok(connected) => connected,
+        Err(e) => {
+            let _ = status.send(ConnectionStatus::Failed(host, e));
+            return;
+        }
+    };
+
+    let mut session = None;
+    let mut lost_at: Option<Instant> = None;
+    let mut attempt = 0;
+    loop {
+        let reason = match connect(&endpoint, server_addr, &name, &color, session).await {
+            Ok((connection, welcome)) => {
+                println!("[client] joined: addr={} slot={}", server_addr, welcome.slot);
+                session = Some(welcome.session);
+                lost_at = None;
+                attempt = 0;
+                let connected = status.send(ConnectionStatus::Connected {
+                    addr: server_addr,
+                    slot: welcome.slot,
+                    name: welcome.name,
+                    resumed: welcome.resumed,
+                    link: open_link(&connection, &received),
+                });
+                // Nobody's listening once the game left the server for another one, or closed
+                if connected.is_err() {
+                    DisconnectReason::Left.close(&connection);
+                    break;
+                }
+                DisconnectReason::from_error(&connection.closed().await)
+            }
+            Err(reason) => reason,
+        };
+        println!("[client] disconnected: addr={} reason={}", server_addr, reason);
 
-    // Waiting for a stream will complete with an error when the server closes the connection
-    let _ = connection.accept_uni().await;
+        let lost_since = *lost_at.get_or_insert_with(Instant::now);
+        if session.is_none() {
+            let _ = status.send(ConnectionStatus::Failed(host, reason.to_string()));
+            break;
+        }
+        if !reason.can_resume() || lost_since.elapsed() >= RECONNECT_GRACE {
+            let _ = status.send(ConnectionStatus::Disconnected(server_addr, reason));
+            break;
+        }
+        attempt += 1;
+        let reconnecting = status.send(ConnectionStatus::Reconnecting {
+            addr: server_addr,
+            attempt,
+            reason,
+        });
+        if reconnecting.is_err() {
+            break;
+        }
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
 
-    // Give the server has a chance to clean up
+    // Give the server a chance to hear the connection is closed
     endpoint.wait_idle().await;
-    
-    Ok(())
-}
\ No newline at end of file
+}
+
+/// Connects to the server and says hello, resuming `session` if there's one.
+async fn connect(
+    endpoint: &Endpoint,
+    server_addr: SocketAddr,
+    name: &str,
+    color: &str,
+    session: Option<u64>,
+) -> Result<(Connection, Welcome), DisconnectReason> {
+    // Connect to the server passing in the server name which is supposed to be in the server certificate.
+    let connecting =
+        endpoint.connect(server_addr, "localhost").map_err(|e| DisconnectReason::ConnectionLost(e.to_string()))?;
+    let connection = connecting.await.map_err(|e| DisconnectReason::from_error(&e))?;
+    let hello = Hello {
+        version: PROTOCOL_VERSION,
+        name: name.to_string(),
+        color: color.to_string(),
+        session,
+    };
+    match handshake(&connection, &hello).await {
+        Ok(welcome) => Ok((connection, welcome)),
+        // A server that won't let the client in closes the connection saying why
+        Err(e) => Err(match connection.close_reason() {
+            Some(error) => DisconnectReason::from_error(&error),
+            None => {
+                DisconnectReason::Left.close(&connection);
+                DisconnectReason::ConnectionLost(e)
+            }
+        }),
+    }
+}
+
+/// Starts talking to the server, handing what it sends to `received`.
+fn open_link(connection: &Connection, received: &Sender<Received>) -> Link {
+    let received = received.clone();
+    Link::open(connection.clone(), move |message| {
+        let _ = received.send(message);
+    })
+}
+
+async fn handshake(connection: &Connection, hello: &Hello) -> Result<Welcome, String> {
+    let (mut send, recv) = connection.open_bi().await.map_err(|e| e.to_string())?;
+    let hello = serde_json::to_vec(hello).map_err(|e| e.to_string())?;
+    send.write_all(&hello).await.map_err(|e| e.to_string())?;
+    send.finish().await.map_err(|e| e.to_string())?;
+    let welcome = recv.read_to_end(MAX_HANDSHAKE_BYTES).await.map_err(|e| e.to_string())?;
+    serde_json::from_slice(&welcome).map_err(|e| e.to_string())
+}
+
+/// Time a handshake with the server at `host` takes to go there and back.
+pub async fn ping(host: &str) -> Result<Duration, Box<dyn Error>> {
+    let server_addr = resolve(host).await?;
+    let endpoint = make_client_endpoint(
+        (Ipv4Addr::UNSPECIFIED, 0).into(),
+        &[],
+        StreamLimits::default(),
+        LinkConditions::default(),
+    )?;
+    let connecting = endpoint.connect(server_addr, "localhost")?;
+    let connection = tokio::time::timeout(PING_TIMEOUT, connecting).await??;
+    let rtt = connection.rtt();
+    connection.close(0_u8.into(), b"ping");
+    Ok(rtt)
+}
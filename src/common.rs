@@ -1,83 +1,167 @@
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::WindowResized;
 use iyes_loopless::prelude::*;
+use rand::Rng;
 
 use components::Size;
 
-use crate::common::components::Position;
-use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
-use crate::snake::components::SnakeHead;
-use crate::snake::spawn_snake;
+use crate::ai::components::{AiController, BotSettings};
+use crate::common::components::{ArenaBackground, ArenaLayout, Direction, Position, Viewport};
+use crate::common::rng::GameRng;
+use crate::mode::components::{GameMode, ModeSettings, Player, Team};
+use crate::player::components::{Identity, LocalIdentity, Skin};
+use crate::player::{free_color, unique_name};
+use crate::snake::components::{LastCell, SnakeHead, SpawnSettings, SpeedSettings, CELL_PROGRESS};
+use crate::snake::{spawn_points, spawn_snake, SNAKE_TICK};
 use crate::state::GameState;
 
 pub mod components;
+pub mod conditioner;
 pub mod constants;
+pub mod discovery;
+pub mod protocol;
 pub mod quinn_helpers;
+pub mod rng;
+pub mod snapshot;
+pub mod transport;
 
 pub struct CommonPlugin;
 
 impl Plugin for CommonPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_camera).add_enter_system(GameState::PreGame, pre_game).add_system_set_to_stage(
-            CoreStage::PostUpdate,
-            ConditionSet::new()
-                .run_in_state(GameState::Running)
-                .with_system(position_translation)
-                .with_system(size_scaling)
-                .into(),
-        );
+        app.init_resource::<Viewport>()
+            .init_resource::<ArenaLayout>()
+            .add_startup_system(setup_arena)
+            .add_enter_system(GameState::PreGame, pre_game)
+            .add_system_to_stage(CoreStage::PreUpdate, update_layout)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                ConditionSet::new()
+                    // Snakes stay on screen while frozen for the countdown, and behind the scoreboard between rounds
+                    .run_not_in_state(GameState::MainMenu)
+                    .with_system(position_translation)
+                    .with_system(size_scaling)
+                    .into(),
+            );
     }
 }
 
-fn size_scaling(windows: Res<Windows>, mut q: Query<(&Size, &mut Transform)>) {
+/// Fits the arena into the viewport again whenever the window is resized or the viewport changes.
+fn update_layout(
+    windows: Res<Windows>,
+    mut resized: EventReader<WindowResized>,
+    viewport: Res<Viewport>,
+    mut layout: ResMut<ArenaLayout>,
+    mut backgrounds: Query<&mut Transform, With<ArenaBackground>>,
+) {
+    // The window doesn't send a resize event for its first size, but the viewport counts as changed then
+    let resized = resized.iter().last().is_some();
+    if !resized && !viewport.is_changed() {
+        return;
+    }
     if let Some(window) = windows.get_primary() {
-        for (sprite_size, mut transform) in q.iter_mut() {
-            transform.scale = Vec3::new(
-                sprite_size.width / ARENA_WIDTH as f32 * window.width() as f32,
-                sprite_size.height / ARENA_HEIGHT as f32 * window.height() as f32,
-                1.0,
-            );
+        *layout = ArenaLayout::fit(window.width(), window.height(), &viewport);
+        for mut transform in backgrounds.iter_mut() {
+            transform.translation = layout.center().extend(-1.0);
+            transform.scale = layout.size().extend(1.0);
         }
     }
 }
 
+fn size_scaling(layout: Res<ArenaLayout>, mut q: Query<(&Size, &mut Transform)>) {
+    for (sprite_size, mut transform) in q.iter_mut() {
+        transform.scale = Vec3::new(sprite_size.width * layout.tile, sprite_size.height * layout.tile, 1.0);
+    }
+}
+
+/// Places everything on the board in its cell. Snakes slide from their last cell towards their current one as
+/// they make progress towards the next move, so they glide rather than jump a cell at a time.
 fn position_translation(
-    windows: Res<Windows>,
-    mut q: Query<(&mut Position, &mut Transform, Option<&SnakeHead>)>, /*, Changed<Position>> */
+    layout: Res<ArenaLayout>,
+    state: Res<CurrentState<GameState>>,
+    timesteps: Res<FixedTimesteps>,
+    speed: Res<SpeedSettings>,
+    heads: Query<(Entity, &SnakeHead)>,
+    mut q: Query<(Entity, &Position, &mut Transform, Option<&LastCell>)>,
 ) {
-    fn convert(pos: f32, bound_window: f32, bound_game: f32) -> f32 {
-        let tile_size = bound_window / bound_game;
-        pos / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
+    // Snakes only make progress while the game is running, so frozen snakes stay put
+    let overstep = match timesteps.get(SNAKE_TICK) {
+        Some(timestep) if state.0 == GameState::Running => timestep.overstep(),
+        _ => 0.0,
+    };
+    let mut progress: HashMap<Entity, f32> = HashMap::new();
+    for (snake, head) in heads.iter() {
+        let cells = (head.progress as f64 + speed.speed(head) as f64 * overstep) / CELL_PROGRESS as f64;
+        for entity in head.tail.iter().chain([&snake]) {
+            progress.insert(*entity, cells.min(1.0) as f32);
+        }
     }
-    if let Some(window) = windows.get_primary() {
-        for (mut pos, mut transform, head) in q.iter_mut() {
-            if pos.x >= ARENA_WIDTH as i32 {
-                pos.x = 0;
-            } else if pos.x < 0 {
-                pos.x = ARENA_WIDTH as i32 - 1;
-            }
 
-            if pos.y >= ARENA_HEIGHT as i32 {
-                pos.y = 0;
-            } else if pos.y < 0 {
-                pos.y = ARENA_HEIGHT as i32 - 1;
+    for (entity, pos, mut transform, last_cell) in q.iter_mut() {
+        // Only ever a step to a neighbouring cell, the short way across the edge when it wraps around
+        let (x, y) = match last_cell.and_then(|last| Some((last.0, last.0.direction_to(*pos)?))) {
+            Some((from, direction)) => {
+                let t = progress.get(&entity).copied().unwrap_or(1.0);
+                let (dx, dy) = match direction {
+                    Direction::Left => (-t, 0.0),
+                    Direction::Up => (0.0, t),
+                    Direction::Right => (t, 0.0),
+                    Direction::Down => (0.0, -t),
+                };
+                (from.x as f32 + dx, from.y as f32 + dy)
             }
+            None => (pos.x as f32, pos.y as f32),
+        };
 
-            let z = if head.is_some() { 1.0 } else { 0.0 };
+        let z = if heads.contains(entity) { 1.0 } else { 0.0 };
 
-            transform.translation = Vec3::new(
-                convert(pos.x as f32, window.width() as f32, ARENA_WIDTH as f32),
-                convert(pos.y as f32, window.height() as f32, ARENA_HEIGHT as f32),
-                z,
-            );
-        }
+        transform.translation = layout.to_world(x, y).extend(z);
     }
 }
 
-fn setup_camera(mut commands: Commands) {
-    commands.spawn_bundle(Camera2dBundle::default());
+fn setup_arena(mut commands: Commands) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.08, 0.08, 0.08),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ArenaBackground);
 }
 
-fn pre_game(mut commands: Commands) {
-    commands.insert_resource(NextState(GameState::Running));
-    spawn_snake(commands);
+fn pre_game(
+    mut commands: Commands,
+    bots: Res<BotSettings>,
+    mode: Res<ModeSettings>,
+    spawns: Res<SpawnSettings>,
+    local: Res<LocalIdentity>,
+    mut rng: ResMut<GameRng>,
+) {
+    let points = spawn_points(bots.count + 1, &spawns);
+    let mut identities: Vec<Identity> = vec![];
+    for (i, (position, direction)) in points.into_iter().enumerate() {
+        let identity = if i == 0 {
+            local.0.clone()
+        } else {
+            Identity {
+                name: unique_name(&format!("Bot {}", i), identities.iter().map(|id| id.name.as_str())),
+                color: free_color(identities.iter().map(|id| &id.color)),
+                skin: Skin::Solid,
+            }
+        };
+        identities.push(identity.clone());
+        let snake = spawn_snake(&mut commands, position, direction, spawns.initial_tail);
+        commands.entity(snake).insert(Player(i)).insert(identity);
+        if mode.mode == GameMode::Team {
+            commands.entity(snake).insert(Team(i % mode.teams.max(1)));
+        }
+        if i > 0 {
+            let brain = bots.strategy.build(rng.0.gen());
+            info!("Adding bot {} playing {}", i, brain.name());
+            commands.entity(snake).insert(AiController { brain });
+        }
+    }
 }
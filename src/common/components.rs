@@ -1,11 +1,35 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, Vec2};
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
+use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
+impl Position {
+    /// Returns the neighbouring cell in `direction`, wrapping around the arena edges.
+    pub fn step(self, direction: Direction) -> Self {
+        let (x, y) = match direction {
+            Direction::Left => (self.x - 1, self.y),
+            Direction::Up => (self.x, self.y + 1),
+            Direction::Right => (self.x + 1, self.y),
+            Direction::Down => (self.x, self.y - 1),
+        };
+        Position {
+            x: x.rem_euclid(ARENA_WIDTH as i32),
+            y: y.rem_euclid(ARENA_HEIGHT as i32),
+        }
+    }
+
+    /// Direction of the neighbouring cell `other`, across the arena edges too, or `None` if it isn't one.
+    pub fn direction_to(self, other: Position) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|direction| self.step(*direction) == other)
+    }
+}
+
 #[derive(Component)]
 pub struct Size {
     pub width: f32,
@@ -18,7 +42,8 @@ impl Size {
     }
 }
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     Left,
     Up,
@@ -27,6 +52,18 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::Left, Direction::Up, Direction::Right, Direction::Down];
+
+    /// The direction a quarter turn anticlockwise from this one.
+    pub fn turn_left(self) -> Self {
+        match self {
+            Self::Up => Self::Left,
+            Self::Left => Self::Down,
+            Self::Down => Self::Right,
+            Self::Right => Self::Up,
+        }
+    }
+
     pub fn opposite(self) -> Self {
         match self {
             Self::Left => Self::Right,
@@ -36,3 +73,73 @@ impl Direction {
         }
     }
 }
+
+/// Room kept free around the arena for HUD panels, in logical pixels from each edge of the window.
+pub struct Viewport {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        // The HUD lists the snakes down the left hand side
+        Self {
+            left: 220.0,
+            right: 20.0,
+            top: 20.0,
+            bottom: 20.0,
+        }
+    }
+}
+
+/// Where the arena is drawn in the window, with square cells as big as fit in the viewport.
+#[derive(Default)]
+pub struct ArenaLayout {
+    /// Side of a cell
+    pub tile: f32,
+    /// Center of the cell at (0, 0)
+    pub origin: Vec2,
+}
+
+impl ArenaLayout {
+    /// Fits the arena into the part of a `width` by `height` window left free by `viewport`, centered in it.
+    pub fn fit(width: f32, height: f32, viewport: &Viewport) -> Self {
+        let free_width = (width - viewport.left - viewport.right).max(0.0);
+        let free_height = (height - viewport.top - viewport.bottom).max(0.0);
+        let tile = (free_width / ARENA_WIDTH as f32).min(free_height / ARENA_HEIGHT as f32);
+        // The camera looks at the middle of the window
+        let center = Vec2::new(
+            (viewport.left - viewport.right) / 2.0,
+            (viewport.bottom - viewport.top) / 2.0,
+        );
+        Self {
+            tile,
+            origin: center - (Self::cells() - Vec2::ONE) * tile / 2.0,
+        }
+    }
+
+    /// Size of the whole arena.
+    pub fn size(&self) -> Vec2 {
+        Self::cells() * self.tile
+    }
+
+    /// Center of the arena.
+    pub fn center(&self) -> Vec2 {
+        self.origin + (Self::cells() - Vec2::ONE) * self.tile / 2.0
+    }
+
+    /// Where the center of cell (`x`, `y`) is drawn, which may be between cells.
+    pub fn to_world(&self, x: f32, y: f32) -> Vec2 {
+        self.origin + Vec2::new(x, y) * self.tile
+    }
+
+    fn cells() -> Vec2 {
+        Vec2::new(ARENA_WIDTH as f32, ARENA_HEIGHT as f32)
+    }
+}
+
+/// Backdrop filling the arena, so the bars around it in windows of another shape show where it ends.
+#[derive(Component)]
+pub struct ArenaBackground;
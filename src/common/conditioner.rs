@@ -0,0 +1,274 @@
+use std::fmt;
+use std::io::{self, IoSliceMut};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use quinn::{AsyncUdpSocket, Transmit};
+use quinn_udp::{RecvMeta, UdpState};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+// A link conditioner sits between an endpoint and its UDP socket, making a real network out of loopback for
+// testing. Every packet going either way is dropped, duplicated or held back by its own spawned task, so
+// packets overtake each other when their delays differ. Only one end of a connection needs it.
+
+/// Extra time a packet picked to be reordered is held back, for the packets after it to overtake it.
+const REORDER_DELAY: Duration = Duration::from_millis(30);
+/// Largest UDP packet
+const MAX_PACKET: usize = 65535;
+
+/// What a conditioned socket does to each packet it sends or receives.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Added to every packet's trip
+    pub latency: Duration,
+    /// Up to this much more is added at random
+    pub jitter: Duration,
+    /// Chance of a packet being lost, from 0 to 1
+    pub loss: f64,
+    /// Chance of a packet arriving twice
+    pub duplication: f64,
+    /// Chance of a packet being held back by [`REORDER_DELAY`]
+    pub reordering: f64,
+    /// Seed for which packets are lost, duplicated or held back, to replay a run. Picked at random if `None`
+    pub seed: Option<u64>,
+}
+
+impl LinkConditions {
+    /// Whether packets go through untouched, so no conditioner is needed.
+    pub fn is_perfect(&self) -> bool {
+        Self { seed: None, ..*self } == Self::default()
+    }
+
+    /// How long each copy of a packet takes to go through, with no copies for a lost packet.
+    fn delays(&self, rng: &mut StdRng) -> Vec<Duration> {
+        if rng.gen_bool(self.loss) {
+            return vec![];
+        }
+        let copies = if rng.gen_bool(self.duplication) { 2 } else { 1 };
+        (0..copies)
+            .map(|_| {
+                let delay = self.latency + self.jitter.mul_f64(rng.gen());
+                if rng.gen_bool(self.reordering) {
+                    delay + REORDER_DELAY
+                } else {
+                    delay
+                }
+            })
+            .collect()
+    }
+}
+
+/// Network conditions picked on the command line, each way of a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkPreset {
+    /// Packets go through untouched
+    Perfect,
+    Lan,
+    Broadband,
+    Mobile,
+    /// A busy network losing a tenth of packets
+    Congested,
+}
+
+impl NetworkPreset {
+    pub const ALL: [NetworkPreset; 5] = [
+        NetworkPreset::Perfect,
+        NetworkPreset::Lan,
+        NetworkPreset::Broadband,
+        NetworkPreset::Mobile,
+        NetworkPreset::Congested,
+    ];
+
+    pub fn conditions(self) -> LinkConditions {
+        let ms = Duration::from_millis;
+        match self {
+            NetworkPreset::Perfect => LinkConditions::default(),
+            NetworkPreset::Lan => LinkConditions {
+                latency: ms(1),
+                jitter: ms(1),
+                ..LinkConditions::default()
+            },
+            NetworkPreset::Broadband => LinkConditions {
+                latency: ms(20),
+                jitter: ms(5),
+                loss: 0.005,
+                duplication: 0.001,
+                reordering: 0.005,
+                seed: None,
+            },
+            NetworkPreset::Mobile => LinkConditions {
+                latency: ms(60),
+                jitter: ms(30),
+                loss: 0.02,
+                duplication: 0.005,
+                reordering: 0.02,
+                seed: None,
+            },
+            NetworkPreset::Congested => LinkConditions {
+                latency: ms(150),
+                jitter: ms(80),
+                loss: 0.1,
+                duplication: 0.02,
+                reordering: 0.05,
+                seed: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for NetworkPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NetworkPreset::Perfect => "perfect",
+            NetworkPreset::Lan => "lan",
+            NetworkPreset::Broadband => "broadband",
+            NetworkPreset::Mobile => "mobile",
+            NetworkPreset::Congested => "congested",
+        })
+    }
+}
+
+impl FromStr for NetworkPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|preset| preset.to_string() == s.to_lowercase()).ok_or_else(|| {
+            format!(
+                "unknown network preset '{}', expected one of perfect, lan, broadband, mobile, congested",
+                s
+            )
+        })
+    }
+}
+
+/// UDP socket putting every packet through [`LinkConditions`], for an endpoint to use instead of a plain one.
+#[derive(Debug)]
+pub struct ConditionedSocket {
+    socket: Arc<UdpSocket>,
+    conditions: LinkConditions,
+    /// Decides the fate of packets sent, apart from the receiving task's so each way replays on its own
+    rng: StdRng,
+    incoming: Mutex<UnboundedReceiver<(Vec<u8>, SocketAddr)>>,
+}
+
+impl ConditionedSocket {
+    /// Binds a socket to `addr`. Must be called on a Tokio runtime.
+    pub fn bind(addr: SocketAddr, conditions: LinkConditions) -> io::Result<Self> {
+        let socket = std::net::UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        let socket = Arc::new(UdpSocket::from_std(socket)?);
+        let (received, incoming) = unbounded_channel();
+        let mut rng = conditions.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        let receiving = StdRng::seed_from_u64(rng.gen());
+        tokio::spawn(receive(socket.clone(), conditions, receiving, received));
+        Ok(Self {
+            socket,
+            conditions,
+            rng,
+            incoming: Mutex::new(incoming),
+        })
+    }
+}
+
+impl AsyncUdpSocket for ConditionedSocket {
+    fn poll_send(&mut self, _state: &UdpState, _cx: &mut Context, transmits: &[Transmit]) -> Poll<io::Result<usize>> {
+        for transmit in transmits {
+            // A transmit may be several packets of the segment size back to back
+            let size = transmit.segment_size.unwrap_or(transmit.contents.len()).max(1);
+            for packet in transmit.contents.chunks(size) {
+                for delay in self.conditions.delays(&mut self.rng) {
+                    let (socket, packet, destination) = (self.socket.clone(), packet.to_vec(), transmit.destination);
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let _ = socket.send_to(&packet, destination).await;
+                    });
+                }
+            }
+        }
+        Poll::Ready(Ok(transmits.len()))
+    }
+
+    fn poll_recv(
+        &self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        let mut incoming = self.incoming.lock().unwrap();
+        match incoming.poll_recv(cx) {
+            Poll::Ready(Some((packet, addr))) => {
+                let len = packet.len().min(bufs[0].len());
+                bufs[0][..len].copy_from_slice(&packet[..len]);
+                meta[0] = RecvMeta {
+                    addr,
+                    len,
+                    stride: len,
+                    ecn: None,
+                    dst_ip: None,
+                };
+                Poll::Ready(Ok(1))
+            }
+            Poll::Ready(None) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+/// Reads packets off `socket`, handing each to `received` once the conditions let it through, until the
+/// conditioned socket is dropped.
+async fn receive(
+    socket: Arc<UdpSocket>,
+    conditions: LinkConditions,
+    mut rng: StdRng,
+    received: UnboundedSender<(Vec<u8>, SocketAddr)>,
+) {
+    let mut buf = vec![0; MAX_PACKET];
+    loop {
+        let (len, addr) = tokio::select! {
+            read = socket.recv_from(&mut buf) => match read {
+                Ok(read) => read,
+                // Like an ICMP error for an earlier packet, which the connection's timeouts deal with
+                Err(_) => continue,
+            },
+            _ = received.closed() => return,
+        };
+        for delay in conditions.delays(&mut rng) {
+            let (received, packet) = (received.clone(), buf[..len].to_vec());
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = received.send((packet, addr));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seed_replays_the_same_packet_fates() {
+        let conditions = NetworkPreset::Congested.conditions();
+        let fates = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..1000).map(|_| conditions.delays(&mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(fates(7), fates(7));
+        assert_ne!(fates(7), fates(8));
+        assert!(LinkConditions {
+            seed: Some(7),
+            ..LinkConditions::default()
+        }
+        .is_perfect());
+    }
+}
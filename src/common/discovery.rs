@@ -0,0 +1,235 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::time::{timeout_at, Instant};
+
+// LAN discovery, as JSON `Packet`s over UDP:
+//
+// - Every server broadcasts an `Announcement` of itself to the announce port once every interval, which
+//   clients listen for to list the servers on the network as they come and go
+// - Clients can also ask right away by sending a `Query` to the query port, which every server listening on it
+//   answers with an `Announcement` straight back to the client. Queries go to the broadcast address and to
+//   loopback, so servers on the same machine are found even without a network to broadcast on
+//
+// Servers are joined on the address their announcement came from and the port in it.
+
+/// Bumped whenever the game's network protocol changes, so players can tell which servers they can join.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Ports and timing of LAN discovery, which tests can move out of the way of a running game.
+#[derive(Clone, Debug)]
+pub struct DiscoveryConfig {
+    /// Port servers listen for queries on
+    pub query_port: u16,
+    /// Port clients listen for broadcast announcements on
+    pub announce_port: u16,
+    /// Time between a server's broadcasts
+    pub interval: Duration,
+    /// Where queries and announcements are broadcast to
+    pub broadcast: IpAddr,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            query_port: 5098,
+            announce_port: 5099,
+            interval: Duration::from_secs(1),
+            broadcast: Ipv4Addr::BROADCAST.into(),
+        }
+    }
+}
+
+/// What a server tells the LAN about itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    /// Port the server accepts QUIC connections on
+    pub port: u16,
+    /// [`PROTOCOL_VERSION`] of the server
+    pub version: u32,
+    pub map: String,
+    pub mode: String,
+    pub players: usize,
+    pub max_players: usize,
+}
+
+impl ServerInfo {
+    /// Whether this game can join the server.
+    pub fn is_compatible(&self) -> bool {
+        self.version == PROTOCOL_VERSION
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Packet {
+    Query { version: u32 },
+    Announcement(ServerInfo),
+}
+
+impl Packet {
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Anything that isn't a packet, from something else that happens to use the port, is `None`.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// Broadcasts the latest `info` and answers queries with it, until its sender is dropped. If another server on
+/// this machine already answers queries, this one only broadcasts.
+pub async fn serve(mut info: watch::Receiver<ServerInfo>, config: DiscoveryConfig) -> io::Result<()> {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.query_port)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            println!("[discovery] not answering queries on port {}: {}", config.query_port, e);
+            UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?
+        }
+    };
+    socket.set_broadcast(true)?;
+    let mut interval = tokio::time::interval(config.interval);
+    let mut warned = false;
+    let mut buf = [0; 1024];
+    loop {
+        let to = tokio::select! {
+            _ = interval.tick() => SocketAddr::new(config.broadcast, config.announce_port),
+            received = socket.recv_from(&mut buf) => match received {
+                Ok((len, from)) => match Packet::from_bytes(&buf[..len]) {
+                    Some(Packet::Query { .. }) => from,
+                    _ => continue,
+                },
+                // Some platforms report an earlier send that nobody received here, which is nothing to stop for
+                Err(_) => continue,
+            }
+        };
+        if info.has_changed().is_err() {
+            return Ok(());
+        }
+        let packet = Packet::Announcement(info.borrow_and_update().clone()).to_bytes()?;
+        // Machines without a network to broadcast on can still play locally, so this is only worth a mention
+        if let Err(e) = socket.send_to(&packet, to).await {
+            if !warned {
+                println!("[discovery] can't announce to {}: {}", to, e);
+                warned = true;
+            }
+        }
+    }
+}
+
+/// Listens for broadcast announcements, handing every server heard from to `found` along with the address to
+/// join it on, until `found`'s receiver is dropped.
+pub async fn listen(found: Sender<(SocketAddr, ServerInfo)>, config: DiscoveryConfig) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.announce_port)).await?;
+    let mut buf = [0; 1024];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        if let Some(Packet::Announcement(info)) = Packet::from_bytes(&buf[..len]) {
+            if found.send((SocketAddr::new(from.ip(), info.port), info)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Asks the servers on the LAN and on this machine about themselves, collecting the answers that arrive within
+/// `timeout`, one per server.
+pub async fn discover(config: &DiscoveryConfig, timeout: Duration) -> io::Result<Vec<(SocketAddr, ServerInfo)>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    let query = Packet::Query {
+        version: PROTOCOL_VERSION,
+    }
+    .to_bytes()?;
+    let targets = [config.broadcast, Ipv4Addr::LOCALHOST.into()];
+    let mut sent = false;
+    for ip in targets {
+        // Either is enough, there may not be a network to broadcast on
+        sent |= socket.send_to(&query, (ip, config.query_port)).await.is_ok();
+    }
+    if !sent {
+        return Err(io::Error::other("couldn't send a query anywhere"));
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut servers: Vec<(SocketAddr, ServerInfo)> = vec![];
+    let mut buf = [0; 1024];
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        // As when serving, a failed receive is about an earlier send
+        let (len, from) = match received {
+            Ok(received) => received,
+            Err(_) => continue,
+        };
+        if let Some(Packet::Announcement(info)) = Packet::from_bytes(&buf[..len]) {
+            let addr = SocketAddr::new(from.ip(), info.port);
+            // A server on this machine can answer on both its LAN address and loopback
+            if !servers.iter().any(|(known, known_info)| known.port() == addr.port() && *known_info == info) {
+                servers.push((addr, info));
+            }
+        }
+    }
+    Ok(servers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loopback_config(query_port: u16, announce_port: u16) -> DiscoveryConfig {
+        DiscoveryConfig {
+            query_port,
+            announce_port,
+            interval: Duration::from_millis(50),
+            broadcast: Ipv4Addr::LOCALHOST.into(),
+        }
+    }
+
+    fn server_info(version: u32) -> ServerInfo {
+        ServerInfo {
+            name: "test server".to_string(),
+            port: 5123,
+            version,
+            map: "default".to_string(),
+            mode: "classic".to_string(),
+            players: 1,
+            max_players: 5,
+        }
+    }
+
+    #[tokio::test]
+    async fn queries_are_answered_on_loopback() {
+        let config = loopback_config(45098, 45099);
+        let (_info, info_rx) = watch::channel(server_info(PROTOCOL_VERSION));
+        tokio::spawn(serve(info_rx, config.clone()));
+        // Give the server time to bind
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let servers = discover(&config, Duration::from_millis(300)).await.unwrap();
+        assert_eq!(servers.len(), 1);
+        let (addr, info) = &servers[0];
+        assert_eq!(*addr, SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 5123));
+        assert_eq!(*info, server_info(PROTOCOL_VERSION));
+        assert!(info.is_compatible());
+    }
+
+    #[tokio::test]
+    async fn announcements_of_other_versions_are_flagged() {
+        let config = loopback_config(46098, 46099);
+        let (found, announcements) = crossbeam_channel::unbounded();
+        tokio::spawn(listen(found, config.clone()));
+        let (_info, info_rx) = watch::channel(server_info(PROTOCOL_VERSION + 1));
+        tokio::spawn(serve(info_rx, config));
+
+        let announcement = tokio::task::spawn_blocking(move || announcements.recv_timeout(Duration::from_secs(2)));
+        let (_, info) = announcement.await.unwrap().unwrap();
+        assert_eq!(info, server_info(PROTOCOL_VERSION + 1));
+        assert!(!info.is_compatible());
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::time::Duration;
+
+use quinn::{Connection, ConnectionError, VarInt};
+use serde::{Deserialize, Serialize};
+
+use crate::common::components::{Direction, Position};
+use crate::common::discovery::PROTOCOL_VERSION;
+
+// Joining a server: once connected, the client opens a bidirectional stream and sends a `Hello` as JSON,
+// finishing the stream. The server answers with a `Welcome` on the same stream, or closes the connection with
+// the `DisconnectReason` it won't let the client in for. A client that loses its connection can send the
+// session from its `Welcome` in a new `Hello` within `RECONNECT_GRACE` to get its player slot back.
+//
+// Once in, the two ends talk over two channels, see `transport`:
+//
+// - `NetMessage`s are events that must all arrive and in order, like joins, deaths and match results
+// - `Datagram`s carry state that's sent every tick, like snapshots, where only the newest matters
+
+/// How long a player slot is kept for a client that lost its connection.
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+/// Largest `Hello` or `Welcome` accepted
+pub const MAX_HANDSHAKE_BYTES: usize = 1024;
+/// Largest `NetMessage` or `Datagram` accepted
+pub const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+    pub name: String,
+    /// In hex, e.g. `#33cc4d`
+    pub color: String,
+    /// Session to resume, from the `Welcome` of the connection that was lost
+    pub session: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Welcome {
+    /// Player slot on the server, starting from 1 as slot 0 is the host's
+    pub slot: usize,
+    pub session: u64,
+    /// Whether the slot is the one the session had before
+    pub resumed: bool,
+    /// Name and color to play as, which the server changes from the `Hello`'s if another player has them
+    pub name: String,
+    pub color: String,
+}
+
+/// Events the other end mustn't miss, sent in order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NetMessage {
+    /// Something for the players to read
+    Notice {
+        text: String,
+    },
+    PlayerJoined {
+        slot: usize,
+        name: String,
+    },
+    /// A player's client left the server, with why
+    PlayerLeft {
+        slot: usize,
+        name: String,
+        reason: String,
+    },
+    Chat {
+        from: String,
+        text: String,
+    },
+    /// A snake died, with the food its tail turned into
+    Died {
+        player: usize,
+        name: String,
+        food: Vec<Position>,
+    },
+    MatchResult {
+        winner: Option<String>,
+        standings: Vec<(String, usize)>,
+    },
+    /// A countdown or intermission ends at `ends_at` on the server's clock, which read `server_time` when this
+    /// was sent. Both are in seconds, see `ServerClock`
+    Phase {
+        server_time: f64,
+        ends_at: f64,
+    },
+}
+
+/// State sent every tick, each newer one replacing the last of its kind, so any that are lost or arrive late
+/// are skipped.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Datagram {
+    /// Counts up from 1 on each connection, in each direction, for each [`StateUpdate::kind`]
+    pub seq: u64,
+    pub state: StateUpdate,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateUpdate {
+    /// The server's whole game as of a tick, for a client without a snapshot to build on
+    Snapshot(Snapshot),
+    /// The server's game as of a tick, as changes to a snapshot the client has acknowledged
+    Delta(SnapshotDelta),
+    /// The newest snapshot a client has, for the server to send changes against
+    Ack { tick: u64 },
+}
+
+/// Number of [`StateUpdate::kind`]s.
+pub const STATE_KINDS: usize = 2;
+
+impl StateUpdate {
+    /// Which updates this one replaces: the game, whole or as changes, or a client's acknowledgement.
+    pub fn kind(&self) -> usize {
+        match self {
+            StateUpdate::Snapshot(_) | StateUpdate::Delta(_) => 0,
+            StateUpdate::Ack { .. } => 1,
+        }
+    }
+}
+
+/// The server's game, see `snapshot` for how it's sent as changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Counts up from 1 for the whole time the server's up, rather than restarting every round
+    pub tick: u64,
+    /// Ordered by player
+    pub snakes: Vec<SnakeState>,
+    /// Ordered by x, then y, with a cell appearing once for each piece of food on it
+    pub food: Vec<Position>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDelta {
+    /// Snapshot the changes are to
+    pub base: u64,
+    pub tick: u64,
+    /// Snakes that changed or are new, ordered by player
+    pub snakes: Vec<SnakeDelta>,
+    /// Players whose snakes are gone
+    pub removed: Vec<usize>,
+    /// A cell for each piece of food added or removed, so twice for two pieces on it
+    pub food_added: Vec<Position>,
+    pub food_removed: Vec<Position>,
+}
+
+/// A snake as the cells it moved into, followed by as many of its cells in the base snapshot as are left.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnakeDelta {
+    pub player: usize,
+    pub direction: Direction,
+    /// Head first
+    pub moved: Vec<Position>,
+    /// Cells kept from the front of the snake in the base snapshot, head first
+    pub kept: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnakeState {
+    /// Player the snake belongs to, see `Player`
+    pub player: usize,
+    pub head: Position,
+    pub direction: Direction,
+    /// Tail segments ordered from the neck to the tip, excluding the head
+    pub body: Vec<Position>,
+}
+
+/// Why a connection ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client left, or a ping was done with the connection
+    Left,
+    Kicked,
+    ServerShutdown,
+    ServerFull,
+    /// The server's protocol version, which isn't this game's
+    VersionMismatch(u32),
+    TimedOut,
+    ConnectionLost(String),
+}
+
+// Application close codes of the reasons that are given on purpose
+const LEFT: u32 = 0;
+const KICKED: u32 = 1;
+const SERVER_SHUTDOWN: u32 = 2;
+const SERVER_FULL: u32 = 3;
+const VERSION_MISMATCH: u32 = 4;
+
+impl DisconnectReason {
+    /// Closes `connection` giving this reason, which is only meaningful for the reasons given on purpose.
+    pub fn close(&self, connection: &Connection) {
+        let (code, reason) = self.close_code();
+        connection.close(code, reason.as_bytes());
+    }
+
+    /// Application close code and reason bytes for this reason.
+    pub fn close_code(&self) -> (VarInt, String) {
+        let (code, reason) = match self {
+            Self::Kicked => (KICKED, "kicked".to_string()),
+            Self::ServerShutdown => (SERVER_SHUTDOWN, "server shutdown".to_string()),
+            Self::ServerFull => (SERVER_FULL, "server full".to_string()),
+            // The reason carries the server's version
+            Self::VersionMismatch(version) => (VERSION_MISMATCH, version.to_string()),
+            Self::Left | Self::TimedOut | Self::ConnectionLost(_) => (LEFT, "left".to_string()),
+        };
+        (VarInt::from_u32(code), reason)
+    }
+
+    /// Reason the connection ended with `error`, as seen from this end.
+    pub fn from_error(error: &ConnectionError) -> Self {
+        match error {
+            ConnectionError::ApplicationClosed(close) => match close.error_code.into_inner() as u32 {
+                KICKED => Self::Kicked,
+                SERVER_SHUTDOWN => Self::ServerShutdown,
+                SERVER_FULL => Self::ServerFull,
+                VERSION_MISMATCH => {
+                    Self::VersionMismatch(String::from_utf8_lossy(&close.reason).parse().unwrap_or_default())
+                }
+                _ => Self::Left,
+            },
+            // Closed on this end, which knows why itself
+            ConnectionError::LocallyClosed => Self::Left,
+            ConnectionError::TimedOut => Self::TimedOut,
+            e => Self::ConnectionLost(e.to_string()),
+        }
+    }
+
+    /// Whether the connection just dropped, so the client can come back to its slot.
+    pub fn can_resume(&self) -> bool {
+        matches!(self, Self::TimedOut | Self::ConnectionLost(_))
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Left => f.write_str("left the game"),
+            Self::Kicked => f.write_str("kicked by the server"),
+            Self::ServerShutdown => f.write_str("the server shut down"),
+            Self::ServerFull => f.write_str("the server is full"),
+            Self::VersionMismatch(version) => write!(
+                f,
+                "the server runs protocol version {} and this game {}",
+                version, PROTOCOL_VERSION
+            ),
+            Self::TimedOut => f.write_str("timed out"),
+            Self::ConnectionLost(e) => write!(f, "connection lost: {}", e),
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use std::error::Error;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use quinn::{ClientConfig, Endpoint, ServerConfig};
+use std::time::Duration;
+use quinn::{ClientConfig, Endpoint, EndpointConfig, ServerConfig, TokioRuntime, TransportConfig, VarInt};
+
+use crate::common::conditioner::{ConditionedSocket, LinkConditions};
+use crate::common::transport::StreamLimits;
+
+/// Connections that hear nothing from the other end for this long are closed as timed out.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often an otherwise quiet connection is kept alive, well within [`IDLE_TIMEOUT`].
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(2);
 
 // Implementation of `ServerCertVerifier` that verifies everything as trustworthy.
 struct SkipServerVerification;
@@ -31,13 +40,17 @@ impl rustls::client::ServerCertVerifier for SkipServerVerification {
 /// ## Args
 ///
 /// - server_certs: list of trusted certificates.
+/// - limits: streams the server may open.
+/// - conditions: network conditions to simulate.
 #[allow(unused)]
 pub fn make_client_endpoint(
     bind_addr: SocketAddr,
     server_certs: &[&[u8]],
+    limits: StreamLimits,
+    conditions: LinkConditions,
 ) -> Result<Endpoint, Box<dyn Error>> {
-    let client_cfg = configure_client(server_certs)?;
-    let mut endpoint = Endpoint::client(bind_addr)?;
+    let client_cfg = configure_client(server_certs, limits)?;
+    let mut endpoint = make_endpoint(bind_addr, None, conditions)?;
     endpoint.set_default_client_config(client_cfg);
     Ok(endpoint)
 }
@@ -50,18 +63,38 @@ pub fn make_client_endpoint(
 /// - a stream of incoming QUIC connections
 /// - server certificate serialized into DER format
 #[allow(unused)]
-pub fn make_server_endpoint(bind_addr: SocketAddr) -> Result<(Endpoint, Vec<u8>), Box<dyn Error>> {
-    let (server_config, server_cert) = configure_server()?;
-    let endpoint = Endpoint::server(server_config, bind_addr)?;
+pub fn make_server_endpoint(
+    bind_addr: SocketAddr,
+    limits: StreamLimits,
+    conditions: LinkConditions,
+) -> Result<(Endpoint, Vec<u8>), Box<dyn Error>> {
+    let (server_config, server_cert) = configure_server(limits)?;
+    let endpoint = make_endpoint(bind_addr, Some(server_config), conditions)?;
     Ok((endpoint, server_cert))
 }
 
+/// Binds an endpoint, on a plain socket unless there are network conditions to simulate.
+fn make_endpoint(
+    bind_addr: SocketAddr,
+    server_config: Option<ServerConfig>,
+    conditions: LinkConditions,
+) -> Result<Endpoint, Box<dyn Error>> {
+    if conditions.is_perfect() {
+        return Ok(match server_config {
+            Some(server_config) => Endpoint::server(server_config, bind_addr)?,
+            None => Endpoint::client(bind_addr)?,
+        });
+    }
+    let socket = ConditionedSocket::bind(bind_addr, conditions)?;
+    Ok(Endpoint::new_with_abstract_socket(EndpointConfig::default(), server_config, socket, TokioRuntime)?)
+}
+
 /// Builds default quinn client config and trusts given certificates.
 ///
 /// ## Args
 ///
 /// - server_certs: a list of trusted certificates in DER format.
-fn configure_client(server_certs: &[&[u8]]) -> Result<ClientConfig, Box<dyn Error>> {
+fn configure_client(server_certs: &[&[u8]], limits: StreamLimits) -> Result<ClientConfig, Box<dyn Error>> {
     // let mut certs = rustls::RootCertStore::empty();
     // for cert in server_certs {
     //     certs.add(&rustls::Certificate(cert.to_vec()))?;
@@ -73,11 +106,16 @@ fn configure_client(server_certs: &[&[u8]]) -> Result<ClientConfig, Box<dyn Erro
         .with_custom_certificate_verifier(SkipServerVerification::new())
         .with_no_client_auth();
 
-    Ok(ClientConfig::new(Arc::new(crypto)))
+    let mut client_config = ClientConfig::new(Arc::new(crypto));
+    let mut transport = TransportConfig::default();
+    configure_transport(&mut transport, limits);
+    client_config.transport_config(Arc::new(transport));
+
+    Ok(client_config)
 }
 
 /// Returns default server configuration along with its certificate.
-fn configure_server() -> Result<(ServerConfig, Vec<u8>), Box<dyn Error>> {
+fn configure_server(limits: StreamLimits) -> Result<(ServerConfig, Vec<u8>), Box<dyn Error>> {
     let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
     let cert_der = cert.serialize_der().unwrap();
     let priv_key = cert.serialize_private_key_der();
@@ -85,9 +123,17 @@ fn configure_server() -> Result<(ServerConfig, Vec<u8>), Box<dyn Error>> {
     let priv_key = rustls::PrivateKey(priv_key);
 
     let mut server_config = ServerConfig::with_single_cert(cert_chain, priv_key)?;
-    Arc::get_mut(&mut server_config.transport)
-        .unwrap()
-        .max_concurrent_uni_streams(0_u8.into());
+    configure_transport(Arc::get_mut(&mut server_config.transport).unwrap(), limits);
 
     Ok((server_config, cert_der))
-}
\ No newline at end of file
+}
+
+/// Applies what both ends have in common: the stream limits, and the idle timeout and keepalive so a dead
+/// connection is noticed on either side.
+fn configure_transport(transport: &mut TransportConfig, limits: StreamLimits) {
+    transport
+        .max_concurrent_bidi_streams(limits.bidi.into())
+        .max_concurrent_uni_streams(limits.uni.into())
+        .max_idle_timeout(Some(VarInt::from_u32(IDLE_TIMEOUT.as_millis() as u32).into()))
+        .keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+}
@@ -0,0 +1,17 @@
+use rand::rngs::StdRng;
+use rand::{random, SeedableRng};
+
+/// Seeded source of randomness for game rules, so a game can be replayed from its seed.
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(random())
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::VecDeque;
+use std::iter;
+
+use bevy::utils::HashMap;
+
+use crate::common::components::Position;
+use crate::common::protocol::{SnakeDelta, SnakeState, Snapshot, SnapshotDelta};
+
+// Snapshots go to each client as changes to the newest snapshot it acknowledged, which the server and client
+// both still have in their `SnapshotHistory`. A client that hasn't acknowledged one yet, or whose
+// acknowledgement is older than the server remembers, gets the whole snapshot instead as a keyframe.
+//
+// A snake that moves keeps most of its cells, just further along: the delta only has the cells it moved into,
+// and how many of the ones it had it still has. Snakes that didn't change are left out.
+//
+// Food is diffed as a count of pieces on each cell, since food dropped by a dying or boosting snake can land
+// on food already there.
+
+/// Snapshots kept to send or apply changes against, a bit over a second and a half's worth.
+pub const SNAPSHOT_HISTORY: usize = 32;
+
+/// The newest snapshots sent or received, by tick.
+#[derive(Default)]
+pub struct SnapshotHistory {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl SnapshotHistory {
+    pub fn get(&self, tick: u64) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.tick == tick)
+    }
+
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+
+    /// Adds `snapshot`, forgetting the oldest one once there are more than [`SNAPSHOT_HISTORY`].
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == SNAPSHOT_HISTORY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+impl SnakeState {
+    /// Head first, then the tail.
+    fn cells(&self) -> impl Iterator<Item = Position> + '_ {
+        iter::once(self.head).chain(self.body.iter().copied())
+    }
+}
+
+impl Snapshot {
+    /// Changes that turn `base` into this snapshot.
+    pub fn delta_from(&self, base: &Snapshot) -> SnapshotDelta {
+        let snakes = self
+            .snakes
+            .iter()
+            .filter_map(|snake| {
+                let cells: Vec<Position> = snake.cells().collect();
+                let old = base.snakes.iter().find(|old| old.player == snake.player);
+                let old_cells: Vec<Position> = old.map_or_else(Vec::new, |old| old.cells().collect());
+                // Taking every cell as moved always works, for a new or respawned snake
+                let moved = (0..=cells.len()).find(|moved| old_cells.starts_with(&cells[*moved..]))?;
+                let unchanged = moved == 0 && cells.len() == old_cells.len();
+                if unchanged && old.is_some_and(|old| old.direction == snake.direction) {
+                    return None;
+                }
+                Some(SnakeDelta {
+                    player: snake.player,
+                    direction: snake.direction,
+                    moved: cells[..moved].to_vec(),
+                    kept: cells.len() - moved,
+                })
+            })
+            .collect();
+        let removed = base
+            .snakes
+            .iter()
+            .map(|old| old.player)
+            .filter(|player| self.snakes.iter().all(|snake| snake.player != *player))
+            .collect();
+
+        // Left with the old food no new food matches, once the new food is taken out of it
+        let mut old_food = count(&base.food);
+        let food_added = self.food.iter().filter(|position| !take(&mut old_food, position)).copied().collect();
+        SnapshotDelta {
+            base: base.tick,
+            tick: self.tick,
+            snakes,
+            removed,
+            food_added,
+            food_removed: base.food.iter().filter(|position| take(&mut old_food, position)).copied().collect(),
+        }
+    }
+
+    /// The snapshot `delta` makes of this one, or `None` if it isn't this one's changes or doesn't fit it.
+    pub fn apply(&self, delta: &SnapshotDelta) -> Option<Snapshot> {
+        if delta.base != self.tick {
+            return None;
+        }
+        let mut snakes: Vec<SnakeState> = self
+            .snakes
+            .iter()
+            .filter(|old| !delta.removed.contains(&old.player))
+            .filter(|old| delta.snakes.iter().all(|changed| changed.player != old.player))
+            .cloned()
+            .collect();
+        for changed in &delta.snakes {
+            let old = self.snakes.iter().find(|old| old.player == changed.player);
+            let old_cells: Vec<Position> = old.map_or_else(Vec::new, |old| old.cells().collect());
+            let kept = old_cells.get(..changed.kept)?;
+            let mut cells = changed.moved.iter().chain(kept).copied();
+            snakes.push(SnakeState {
+                player: changed.player,
+                head: cells.next()?,
+                direction: changed.direction,
+                body: cells.collect(),
+            });
+        }
+        snakes.sort_by_key(|snake| snake.player);
+
+        let mut removed = count(&delta.food_removed);
+        let mut food: Vec<Position> = self
+            .food
+            .iter()
+            .filter(|position| !take(&mut removed, position))
+            .chain(&delta.food_added)
+            .copied()
+            .collect();
+        if !removed.is_empty() {
+            return None;
+        }
+        sort_food(&mut food);
+        Some(Snapshot {
+            tick: delta.tick,
+            snakes,
+            food,
+        })
+    }
+}
+
+/// How many pieces of food are on each cell.
+fn count(food: &[Position]) -> HashMap<Position, usize> {
+    let mut counts = HashMap::new();
+    for position in food {
+        *counts.entry(*position).or_default() += 1;
+    }
+    counts
+}
+
+/// Takes one piece of food at `position` out of `counts`, returning whether there was one.
+fn take(counts: &mut HashMap<Position, usize>, position: &Position) -> bool {
+    match counts.get_mut(position) {
+        Some(1) => {
+            counts.remove(position);
+            true
+        }
+        Some(pieces) => {
+            *pieces -= 1;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Puts food in the order snapshots keep it in, so the same food makes the same snapshot.
+pub fn sort_food(food: &mut [Position]) {
+    food.sort_by_key(|position| (position.x, position.y));
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::common::components::Direction;
+
+    /// Plays a made up game of snakes moving, growing, shrinking, dying and respawning, and food coming and
+    /// going, returning its snapshot at every tick.
+    fn play(rng: &mut StdRng, ticks: u64) -> Vec<Snapshot> {
+        let mut snakes: Vec<SnakeState> = vec![];
+        let mut food: Vec<Position> = vec![];
+        let mut snapshots = vec![];
+        for tick in 1..=ticks {
+            for snake in &mut snakes {
+                if rng.gen_bool(0.2) {
+                    let turns = [
+                        snake.direction,
+                        snake.direction.turn_left(),
+                        snake.direction.turn_left().opposite(),
+                    ];
+                    snake.direction = turns[rng.gen_range(0..turns.len())];
+                }
+                // Boosting snakes move two cells a tick
+                for _ in 0..rng.gen_range(0..=2) {
+                    snake.body.insert(0, snake.head);
+                    snake.head = snake.head.step(snake.direction);
+                    // Growing, keeping the same length or shrinking, down to a snake without a tail
+                    match rng.gen_range(0..10) {
+                        0 => {}
+                        1 => {
+                            snake.body.truncate(snake.body.len().saturating_sub(2));
+                        }
+                        _ => {
+                            snake.body.pop();
+                        }
+                    }
+                }
+            }
+            snakes.retain(|_| rng.gen_bool(0.98));
+            for player in 0..8 {
+                if snakes.iter().all(|snake| snake.player != player) && rng.gen_bool(0.1) {
+                    let head = Position {
+                        x: rng.gen_range(0..40),
+                        y: rng.gen_range(0..40),
+                    };
+                    let direction = Direction::ALL[rng.gen_range(0..4)];
+                    snakes.push(SnakeState {
+                        player,
+                        head,
+                        direction,
+                        // Yet to unfold from the head
+                        body: vec![head; rng.gen_range(0..5)],
+                    });
+                }
+            }
+            snakes.sort_by_key(|snake| snake.player);
+
+            food.retain(|_| rng.gen_bool(0.95));
+            // On a board this small food often lands on food, like it can in a game
+            for _ in 0..rng.gen_range(0..3) {
+                food.push(Position {
+                    x: rng.gen_range(0..8),
+                    y: rng.gen_range(0..8),
+                });
+            }
+            sort_food(&mut food);
+
+            snapshots.push(Snapshot {
+                tick,
+                snakes: snakes.clone(),
+                food: food.clone(),
+            });
+        }
+        snapshots
+    }
+
+    #[test]
+    fn snapshots_rebuilt_from_deltas_match_the_server() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut server = SnapshotHistory::default();
+            let mut client = SnapshotHistory::default();
+            let mut ack = None;
+            let mut deltas = 0;
+            for snapshot in play(&mut rng, 500) {
+                let base = ack.and_then(|tick| server.get(tick));
+                let delta = base.map(|base| snapshot.delta_from(base));
+                server.push(snapshot.clone());
+                // Snapshots and acknowledgements both get lost on the way
+                if rng.gen_bool(0.3) {
+                    continue;
+                }
+                let rebuilt = match delta {
+                    Some(delta) => {
+                        deltas += 1;
+                        client.get(delta.base).and_then(|base| base.apply(&delta)).unwrap()
+                    }
+                    None => snapshot.clone(),
+                };
+                assert_eq!(rebuilt, snapshot, "seed {}", seed);
+                client.push(rebuilt);
+                if rng.gen_bool(0.7) {
+                    ack = Some(snapshot.tick);
+                }
+            }
+            assert!(deltas > 300, "seed {} only sent {} deltas", seed, deltas);
+        }
+    }
+
+    #[test]
+    fn delta_of_a_moving_snake_only_has_its_new_cells() {
+        let snake = |head: Position, body: Vec<Position>| SnakeState {
+            player: 1,
+            head,
+            direction: Direction::Right,
+            body,
+        };
+        let cell = |x| Position { x, y: 0 };
+        let base = Snapshot {
+            tick: 1,
+            snakes: vec![snake(cell(10), (0..10).rev().map(cell).collect())],
+            food: vec![],
+        };
+        let next = Snapshot {
+            tick: 2,
+            snakes: vec![snake(cell(11), (1..11).rev().map(cell).collect())],
+            food: vec![],
+        };
+        let delta = next.delta_from(&base);
+        assert_eq!(
+            delta.snakes,
+            vec![SnakeDelta {
+                player: 1,
+                direction: Direction::Right,
+                moved: vec![cell(11)],
+                kept: 10,
+            }]
+        );
+        assert_eq!(base.apply(&delta), Some(next));
+    }
+
+    #[test]
+    fn delta_of_stacked_food_only_has_the_pieces_that_changed() {
+        let cell = |x| Position { x, y: 0 };
+        let snapshot = |tick, food| Snapshot {
+            tick,
+            snakes: vec![],
+            food,
+        };
+        let base = snapshot(1, vec![cell(1), cell(1), cell(2)]);
+        let next = snapshot(2, vec![cell(1), cell(2), cell(2), cell(2)]);
+        let delta = next.delta_from(&base);
+        assert_eq!(delta.food_added, vec![cell(2), cell(2)]);
+        assert_eq!(delta.food_removed, vec![cell(1)]);
+        assert_eq!(base.apply(&delta), Some(next));
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use quinn::{Connection, RecvStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+
+use crate::common::protocol::{Datagram, NetMessage, StateUpdate, MAX_MESSAGE_BYTES, STATE_KINDS};
+
+// How the two channels of `protocol` travel over a connection:
+//
+// - Each end opens one unidirectional stream to the other for its `NetMessage`s, each a big endian u32 length
+//   followed by that much JSON, so they all arrive and in the order they were sent
+// - `Datagram`s go as QUIC datagrams of JSON. The receiver drops any that aren't newer than the newest of their
+//   kind so far.
+//   One that's too big for a datagram goes on a unidirectional stream of its own instead, where it can still be
+//   overtaken, and dropped, like a datagram. Only one such stream of each kind is open at a time, so they can't
+//   pile up waiting for the other end's stream limit, and a state still waiting for its stream gives way to a
+//   newer one of its kind
+//
+// Unidirectional streams start with a byte saying which of the two kinds they are.
+
+const EVENT_STREAM: u8 = 0;
+const STATE_STREAM: u8 = 1;
+
+/// Streams each endpoint lets the other have open at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamLimits {
+    /// Only needed to say hello
+    pub bidi: u32,
+    /// The event stream, and any states too big for a datagram still being sent
+    pub uni: u32,
+}
+
+impl Default for StreamLimits {
+    fn default() -> Self {
+        Self { bidi: 1, uni: 8 }
+    }
+}
+
+/// Something the other end of a [`Link`] sent.
+#[derive(Debug)]
+pub enum Received {
+    Message(NetMessage),
+    State(StateUpdate),
+}
+
+type Handler = Arc<dyn Fn(Received) + Send + Sync>;
+/// Sequence number of the newest state of each kind received
+type Newest = [AtomicU64; STATE_KINDS];
+
+/// Both channels to the other end of a connection.
+#[derive(Clone, Debug)]
+pub struct Link {
+    connection: Connection,
+    messages: UnboundedSender<NetMessage>,
+    states: UnboundedSender<StateUpdate>,
+}
+
+impl Link {
+    /// Starts talking over `connection`, handing what the other end sends to `received` until the connection
+    /// closes. Must be called on a Tokio runtime, but the link can be sent on from anywhere.
+    pub fn open(connection: Connection, received: impl Fn(Received) + Send + Sync + 'static) -> Self {
+        let received: Handler = Arc::new(received);
+        let newest = Arc::new(Newest::default());
+        let (messages, outgoing_messages) = unbounded_channel();
+        let (states, outgoing_states) = unbounded_channel();
+        tokio::spawn(write_messages(connection.clone(), outgoing_messages));
+        tokio::spawn(write_states(connection.clone(), outgoing_states));
+        tokio::spawn(read_datagrams(connection.clone(), newest.clone(), received.clone()));
+        tokio::spawn(accept_streams(connection.clone(), newest, received));
+        Self {
+            connection,
+            messages,
+            states,
+        }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Sends `message` after every message sent before it.
+    pub fn send(&self, message: NetMessage) {
+        // Only fails once the connection is gone
+        let _ = self.messages.send(message);
+    }
+
+    /// Sends `state` to replace the state sent before it.
+    pub fn send_state(&self, state: StateUpdate) {
+        let _ = self.states.send(state);
+    }
+}
+
+async fn write_messages(connection: Connection, mut outgoing: UnboundedReceiver<NetMessage>) {
+    let mut stream = match connection.open_uni().await {
+        Ok(stream) => stream,
+        Err(_) => return,
+    };
+    if stream.write_all(&[EVENT_STREAM]).await.is_err() {
+        return;
+    }
+    while let Some(message) = outgoing.recv().await {
+        let bytes = match serde_json::to_vec(&message) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        let len = (bytes.len() as u32).to_be_bytes();
+        if stream.write_all(&len).await.is_err() || stream.write_all(&bytes).await.is_err() {
+            return;
+        }
+    }
+    let _ = stream.finish().await;
+}
+
+async fn write_states(connection: Connection, mut outgoing: UnboundedReceiver<StateUpdate>) {
+    let mut seqs = [0; STATE_KINDS];
+    // The newest state of each kind that's too big for a datagram
+    let oversized: Vec<watch::Sender<Vec<u8>>> = (0..STATE_KINDS)
+        .map(|_| {
+            let (oversized, newest) = watch::channel(vec![]);
+            tokio::spawn(write_oversized_states(connection.clone(), newest));
+            oversized
+        })
+        .collect();
+    while let Some(state) = outgoing.recv().await {
+        let kind = state.kind();
+        let seq = &mut seqs[kind];
+        *seq += 1;
+        let bytes = match serde_json::to_vec(&Datagram { seq: *seq, state }) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        // No size means the other end doesn't take datagrams at all
+        if connection.max_datagram_size().is_some_and(|max| bytes.len() <= max) {
+            if connection.send_datagram(bytes.into()).is_err() {
+                return;
+            }
+            continue;
+        }
+        let _ = oversized[kind].send(bytes);
+    }
+}
+
+/// Sends states of one kind on a stream each, one after the other, until `write_states` is done. Those that
+/// came in while waiting for a stream are skipped for the newest.
+async fn write_oversized_states(connection: Connection, mut newest: watch::Receiver<Vec<u8>>) {
+    while newest.changed().await.is_ok() {
+        let mut stream = match connection.open_uni().await {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        let bytes = newest.borrow_and_update().clone();
+        if stream.write_all(&[STATE_STREAM]).await.is_ok() && stream.write_all(&bytes).await.is_ok() {
+            let _ = stream.finish().await;
+        }
+    }
+}
+
+async fn read_datagrams(connection: Connection, newest: Arc<Newest>, received: Handler) {
+    while let Ok(bytes) = connection.read_datagram().await {
+        accept_state(&bytes, &newest, &received);
+    }
+}
+
+async fn accept_streams(connection: Connection, newest: Arc<Newest>, received: Handler) {
+    while let Ok(mut stream) = connection.accept_uni().await {
+        let (newest, received) = (newest.clone(), received.clone());
+        tokio::spawn(async move {
+            let mut kind = [0];
+            if stream.read_exact(&mut kind).await.is_err() {
+                return;
+            }
+            match kind[0] {
+                EVENT_STREAM => read_messages(stream, &received).await,
+                STATE_STREAM => {
+                    if let Ok(bytes) = stream.read_to_end(MAX_MESSAGE_BYTES).await {
+                        accept_state(&bytes, &newest, &received);
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+}
+
+async fn read_messages(mut stream: RecvStream, received: &Handler) {
+    let mut len = [0; 4];
+    while stream.read_exact(&mut len).await.is_ok() {
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_MESSAGE_BYTES {
+            return;
+        }
+        let mut bytes = vec![0; len];
+        if stream.read_exact(&mut bytes).await.is_err() {
+            return;
+        }
+        if let Ok(message) = serde_json::from_slice(&bytes) {
+            received(Received::Message(message));
+        }
+    }
+}
+
+/// Hands on the state in `bytes` if it's newer than any of its kind handed on before.
+fn accept_state(bytes: &[u8], newest: &Newest, received: &Handler) {
+    if let Ok(Datagram { seq, state }) = serde_json::from_slice::<Datagram>(bytes) {
+        if newest[state.kind()].fetch_max(seq, Ordering::Relaxed) < seq {
+            received(Received::State(state));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::common::components::Position;
+    use crate::common::conditioner::LinkConditions;
+    use crate::common::protocol::Snapshot;
+    use crate::common::quinn_helpers::{make_client_endpoint, make_server_endpoint};
+
+    #[tokio::test]
+    async fn the_newest_oversized_state_gets_through_a_tight_stream_limit() {
+        let limits = StreamLimits { bidi: 1, uni: 2 };
+        let loopback = (Ipv4Addr::LOCALHOST, 0).into();
+        let (server, _) = make_server_endpoint(loopback, limits, LinkConditions::default()).unwrap();
+        let client = make_client_endpoint(loopback, &[], limits, LinkConditions::default()).unwrap();
+        let connecting = client.connect(server.local_addr().unwrap(), "localhost").unwrap();
+        let (accepted, connected) = tokio::join!(server.accept(), connecting);
+        let (incoming, states) = crossbeam_channel::unbounded();
+        let _server_link = Link::open(accepted.unwrap().await.unwrap(), move |received| {
+            if let Received::State(StateUpdate::Snapshot(snapshot)) = received {
+                let _ = incoming.send(snapshot.tick);
+            }
+        });
+        let client_link = Link::open(connected.unwrap(), |_| {});
+
+        // Far too much food for a datagram
+        let food: Vec<Position> = (0..500).map(|x| Position { x, y: 0 }).collect();
+        for tick in 1..=100 {
+            client_link.send_state(StateUpdate::Snapshot(Snapshot {
+                tick,
+                snakes: vec![],
+                food: food.clone(),
+            }));
+        }
+
+        let ticks = tokio::task::spawn_blocking(move || {
+            let mut ticks = vec![];
+            while let Ok(tick) = states.recv_timeout(Duration::from_secs(5)) {
+                ticks.push(tick);
+                if tick == 100 {
+                    break;
+                }
+            }
+            ticks
+        });
+        let ticks = ticks.await.unwrap();
+        assert_eq!(ticks.last(), Some(&100));
+        assert!(
+            ticks.windows(2).all(|pair| pair[0] < pair[1]),
+            "older states got through: {:?}",
+            ticks
+        );
+    }
+}
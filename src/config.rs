@@ -0,0 +1,165 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use serde::{Deserialize, Serialize};
+
+use crate::player::components::{LocalIdentity, PALETTE};
+use crate::player::{color_hex, parse_color};
+use crate::server::server::SERVER_PORT;
+use crate::snake::components::KeyBindings;
+
+/// Window sizes offered in the settings menu.
+pub const WINDOW_SIZES: [(f32, f32); 4] = [(800.0, 800.0), (1000.0, 1000.0), (1200.0, 1000.0), (1600.0, 1200.0)];
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_config);
+    }
+}
+
+/// Preferences kept between runs, in `config.json` in the user's config directory. Settings missing from the
+/// file keep their defaults, so older files still load.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    pub window: WindowConfig,
+    pub keys: KeyBindings,
+    pub name: String,
+    /// In hex, e.g. `#33cc4d`
+    pub color: String,
+    /// Server to connect to unless told otherwise
+    pub server: SocketAddr,
+    /// Servers joined lately as they were typed in, most recent first
+    pub recent_servers: Vec<String>,
+    pub show_fps: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+    pub fullscreen: bool,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            keys: KeyBindings::default(),
+            name: "Player".to_string(),
+            color: color_hex(PALETTE[0]),
+            server: ([127, 0, 0, 1], SERVER_PORT).into(),
+            recent_servers: vec![],
+            show_fps: false,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 1200.0,
+            height: 1000.0,
+            fullscreen: false,
+        }
+    }
+}
+
+impl UserConfig {
+    /// Where the config is kept, if there's a home directory to keep it in.
+    pub fn path() -> Option<PathBuf> {
+        let dir = if cfg!(windows) {
+            PathBuf::from(env::var_os("APPDATA")?)
+        } else if cfg!(target_os = "macos") {
+            PathBuf::from(env::var_os("HOME")?).join("Library/Application Support")
+        } else {
+            match env::var_os("XDG_CONFIG_HOME") {
+                Some(dir) => PathBuf::from(dir),
+                None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+            }
+        };
+        Some(dir.join("snakegame").join("config.json"))
+    }
+
+    /// Reads the saved config, falling back to the defaults if there isn't one or it can't be read.
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+        match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("WARNING: ignoring invalid config {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                eprintln!("WARNING: couldn't read config {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "Snake!".to_string(),
+            width: self.window.width,
+            height: self.window.height,
+            mode: window_mode(self.window.fullscreen),
+            // TODO: always opens on primary monitor, can't find the Current monitor for some reason
+            position: WindowPosition::Centered(MonitorSelection::Primary),
+            ..default()
+        }
+    }
+}
+
+fn window_mode(fullscreen: bool) -> WindowMode {
+    if fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    }
+}
+
+/// Applies changes made in the settings menu as they're made. The config the game started with was already
+/// applied in `main`, where the command line gets a say too.
+fn apply_config(
+    config: Res<UserConfig>,
+    mut windows: ResMut<Windows>,
+    mut bindings: ResMut<KeyBindings>,
+    mut identity: ResMut<LocalIdentity>,
+) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = window_mode(config.window.fullscreen);
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+        if (window.requested_width(), window.requested_height()) != (config.window.width, config.window.height) {
+            window.set_resolution(config.window.width, config.window.height);
+        }
+    }
+    *bindings = config.keys.clone();
+    identity.0.name = config.name.clone();
+    if let Ok(color) = parse_color(&config.color) {
+        identity.0.color = color;
+    }
+}
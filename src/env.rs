@@ -0,0 +1,382 @@
+use bevy::prelude::Entity;
+
+use crate::ai::board::SnakeView;
+use crate::common::components::{Direction, Position};
+use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
+use crate::simulation::Simulation;
+use crate::snake::components::SpawnSettings;
+use crate::snake::spawn_points;
+
+// Channels of a grid observation, from the observing agent's point of view
+pub const CHANNEL_OWN_HEAD: usize = 0;
+pub const CHANNEL_OWN_TAIL: usize = 1;
+pub const CHANNEL_OTHER_HEADS: usize = 2;
+pub const CHANNEL_OTHER_TAILS: usize = 3;
+pub const CHANNEL_FOOD: usize = 4;
+/// Arenas don't have walls yet so this is always empty, but keeps observations the same shape once they do.
+pub const CHANNEL_WALLS: usize = 5;
+pub const GRID_CHANNELS: usize = 6;
+
+#[derive(Clone, Copy, Debug)]
+pub enum ObservationKind {
+    /// The whole arena, with row `y` and column `x` holding the cell at `Position { x, y }`.
+    Grid,
+    /// A `2 * radius + 1` square centred on the agent's head and rotated so the agent faces the top row.
+    Egocentric { radius: usize },
+}
+
+/// Rewards handed out to each agent every step.
+#[derive(Clone, Copy, Debug)]
+pub struct RewardConfig {
+    /// Per segment grown
+    pub food: f32,
+    /// On the step the agent's snake dies
+    pub death: f32,
+    /// Every step the agent is alive, e.g. a small negative value to discourage stalling
+    pub step: f32,
+    /// To the last snake standing in a multi-agent episode
+    pub win: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            food: 1.0,
+            death: -1.0,
+            step: 0.0,
+            win: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnvConfig {
+    pub agents: usize,
+    pub observation: ObservationKind,
+    pub rewards: RewardConfig,
+    /// Episodes are cut off after this many simulation ticks, of which a step takes one or more
+    pub max_ticks: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            agents: 1,
+            observation: ObservationKind::Grid,
+            rewards: RewardConfig::default(),
+            max_ticks: 4000,
+        }
+    }
+}
+
+/// Dense `channels x height x width` tensor, stored channel by channel and then row by row.
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub channels: usize,
+    pub height: usize,
+    pub width: usize,
+    pub data: Vec<f32>,
+}
+
+impl Observation {
+    fn zeros(channels: usize, height: usize, width: usize) -> Self {
+        Self {
+            channels,
+            height,
+            width,
+            data: vec![0.0; channels * height * width],
+        }
+    }
+
+    fn set(&mut self, channel: usize, row: usize, col: usize) {
+        self.data[(channel * self.height + row) * self.width + col] = 1.0;
+    }
+
+    pub fn get(&self, channel: usize, row: usize, col: usize) -> f32 {
+        self.data[(channel * self.height + row) * self.width + col]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StepInfo {
+    pub tick: u64,
+    /// Length of each agent's snake, as of its last step alive
+    pub lengths: Vec<usize>,
+    pub alive: Vec<bool>,
+}
+
+/// Gym-style environment over a headless [`Simulation`], so agents train against the same rules as the game.
+///
+/// Agent `i` always controls the same snake within an episode, and acts by choosing the direction to turn to
+/// each step. Reversing is ignored just like keyboard input. Snakes only move every few ticks, so a step runs
+/// as many ticks as it takes for some agent's snake to move, and every action counts.
+pub struct SnakeEnv {
+    config: EnvConfig,
+    sim: Simulation,
+    snakes: Vec<Entity>,
+    lengths: Vec<usize>,
+    alive: Vec<bool>,
+}
+
+impl SnakeEnv {
+    pub fn new(config: EnvConfig) -> Self {
+        let mut env = Self {
+            config,
+            sim: Simulation::new(0),
+            snakes: vec![],
+            lengths: vec![],
+            alive: vec![],
+        };
+        env.reset(0);
+        env
+    }
+
+    /// Starts a new episode, returning each agent's first observation.
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.sim = Simulation::new(seed);
+        self.snakes = spawn_points(self.config.agents, &SpawnSettings::default())
+            .into_iter()
+            .map(|(position, direction)| self.sim.spawn_player(position, direction))
+            .collect();
+        self.lengths = self.snakes.iter().map(|snake| self.sim.snake_length(*snake).unwrap()).collect();
+        self.alive = vec![true; self.config.agents];
+        self.observe()
+    }
+
+    /// Applies one action per agent and advances the game until an agent's snake moves or dies.
+    ///
+    /// Returns each agent's observation and reward, whether the episode is over, and extra info.
+    pub fn step(&mut self, actions: &[Direction]) -> (Vec<Observation>, Vec<f32>, bool, StepInfo) {
+        assert_eq!(actions.len(), self.snakes.len(), "expected one action per agent");
+        for (snake, action) in self.snakes.iter().zip(actions) {
+            self.sim.steer(*snake, *action);
+        }
+        let heads: Vec<Option<Position>> = self.heads();
+        loop {
+            self.sim.step();
+            if self.heads() != heads || self.sim.tick >= self.config.max_ticks {
+                break;
+            }
+        }
+
+        let rewards = self.config.rewards;
+        let mut reward = vec![0.0; self.snakes.len()];
+        for (i, snake) in self.snakes.iter().enumerate() {
+            if !self.alive[i] {
+                continue;
+            }
+            match self.sim.snake_length(*snake) {
+                Some(length) => {
+                    reward[i] += rewards.step + rewards.food * length.saturating_sub(self.lengths[i]) as f32;
+                    self.lengths[i] = length;
+                }
+                None => {
+                    reward[i] += rewards.death;
+                    self.alive[i] = false;
+                }
+            }
+        }
+
+        let alive = self.alive.iter().filter(|alive| **alive).count();
+        let done = alive == 0 || (self.snakes.len() > 1 && alive <= 1) || self.sim.tick >= self.config.max_ticks;
+        if done && self.snakes.len() > 1 && alive == 1 {
+            reward[self.alive.iter().position(|alive| *alive).unwrap()] += rewards.win;
+        }
+
+        let info = StepInfo {
+            tick: self.sim.tick,
+            lengths: self.lengths.clone(),
+            alive: self.alive.clone(),
+        };
+        (self.observe(), reward, done, info)
+    }
+
+    /// Where each agent's snake's head is, or `None` once it's dead.
+    fn heads(&self) -> Vec<Option<Position>> {
+        self.snakes.iter().map(|snake| self.sim.snake_view(*snake).map(|view| view.head)).collect()
+    }
+
+    fn observe(&mut self) -> Vec<Observation> {
+        let views: Vec<Option<SnakeView>> = self.snakes.iter().map(|snake| self.sim.snake_view(*snake)).collect();
+        let food = self.sim.food();
+        (0..self.snakes.len())
+            .map(|me| match self.config.observation {
+                ObservationKind::Grid => grid_observation(&views, &food, me),
+                ObservationKind::Egocentric { radius } => egocentric_observation(&views, &food, me, radius),
+            })
+            .collect()
+    }
+}
+
+/// Calls `mark(channel, position)` for every object on the board, from agent `me`'s point of view.
+fn for_each_cell(views: &[Option<SnakeView>], food: &[Position], me: usize, mut mark: impl FnMut(usize, Position)) {
+    for (i, view) in views.iter().enumerate() {
+        if let Some(view) = view {
+            let (head, tail) =
+                if i == me { (CHANNEL_OWN_HEAD, CHANNEL_OWN_TAIL) } else { (CHANNEL_OTHER_HEADS, CHANNEL_OTHER_TAILS) };
+            mark(head, view.head);
+            for segment in &view.body {
+                mark(tail, *segment);
+            }
+        }
+    }
+    for position in food {
+        mark(CHANNEL_FOOD, *position);
+    }
+}
+
+fn grid_observation(views: &[Option<SnakeView>], food: &[Position], me: usize) -> Observation {
+    let mut obs = Observation::zeros(GRID_CHANNELS, ARENA_HEIGHT as usize, ARENA_WIDTH as usize);
+    for_each_cell(views, food, me, |channel, pos| {
+        obs.set(channel, pos.y as usize, pos.x as usize)
+    });
+    obs
+}
+
+fn egocentric_observation(views: &[Option<SnakeView>], food: &[Position], me: usize, radius: usize) -> Observation {
+    let size = 2 * radius + 1;
+    let mut obs = Observation::zeros(GRID_CHANNELS, size, size);
+    // A dead agent sees nothing
+    let (head, facing) = match &views[me] {
+        Some(view) => (view.head, view.direction),
+        None => return obs,
+    };
+
+    let r = radius as i32;
+    for_each_cell(views, food, me, |channel, pos| {
+        // Offset from the head, wrapped into [-half, half) so cells across an edge count as nearby
+        let wrap = |d: i32, bound: u32| (d + bound as i32 / 2).rem_euclid(bound as i32) - bound as i32 / 2;
+        let dx = wrap(pos.x - head.x, ARENA_WIDTH);
+        let dy = wrap(pos.y - head.y, ARENA_HEIGHT);
+        let (right, forward) = match facing {
+            Direction::Up => (dx, dy),
+            Direction::Right => (-dy, dx),
+            Direction::Down => (-dx, -dy),
+            Direction::Left => (dy, -dx),
+        };
+        if right.abs() <= r && forward.abs() <= r {
+            obs.set(channel, (r - forward) as usize, (right + r) as usize);
+        }
+    });
+    obs
+}
+
+/// Batch of independent [`SnakeEnv`]s stepped together, resetting each one as soon as its episode ends.
+pub struct VecSnakeEnv {
+    envs: Vec<SnakeEnv>,
+    next_seed: u64,
+}
+
+impl VecSnakeEnv {
+    pub fn new(config: EnvConfig, count: usize) -> Self {
+        Self {
+            envs: (0..count).map(|_| SnakeEnv::new(config)).collect(),
+            next_seed: 0,
+        }
+    }
+
+    /// Resets every environment, seeding environment `i` with `seed + i`.
+    pub fn reset(&mut self, seed: u64) -> Vec<Vec<Observation>> {
+        let observations = self.envs.iter_mut().zip(seed..).map(|(env, seed)| env.reset(seed)).collect();
+        self.next_seed = seed + self.envs.len() as u64;
+        observations
+    }
+
+    /// Steps every environment with its agents' actions. Environments whose episode ended are reset with a
+    /// fresh seed, and the observation returned for them is the first of the new episode.
+    pub fn step(&mut self, actions: &[Vec<Direction>]) -> Vec<(Vec<Observation>, Vec<f32>, bool, StepInfo)> {
+        assert_eq!(actions.len(), self.envs.len(), "expected actions for every environment");
+        let mut results = vec![];
+        for (env, actions) in self.envs.iter_mut().zip(actions) {
+            let (mut observations, rewards, done, info) = env.step(actions);
+            if done {
+                observations = env.reset(self.next_seed);
+                self.next_seed += 1;
+            }
+            results.push((observations, rewards, done, info));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::components::FoodKind;
+
+    #[test]
+    fn every_step_moves_a_snake_one_cell_the_way_it_was_steered() {
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        let mut head = env.heads()[0].unwrap();
+        let mut direction = env.sim.snake_view(env.snakes[0]).unwrap().direction;
+        for step in 0..20 {
+            // Alternate going straight on and turning
+            if step % 2 == 1 {
+                direction = direction.turn_left();
+            }
+            let (_, _, done, _) = env.step(&[direction]);
+            assert!(!done);
+            let moved_to = env.heads()[0].unwrap();
+            assert_eq!(moved_to, head.step(direction), "step {}", step);
+            head = moved_to;
+        }
+    }
+
+    #[test]
+    fn observations_have_the_documented_shape() {
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        let head = env.heads()[0].unwrap();
+        let grid = env.reset(0).remove(0);
+        assert_eq!(
+            (grid.channels, grid.height, grid.width),
+            (GRID_CHANNELS, ARENA_HEIGHT as usize, ARENA_WIDTH as usize)
+        );
+        assert_eq!(
+            grid.data.len(),
+            GRID_CHANNELS * ARENA_HEIGHT as usize * ARENA_WIDTH as usize
+        );
+        assert_eq!(grid.get(CHANNEL_OWN_HEAD, head.y as usize, head.x as usize), 1.0);
+        assert!(grid.data.iter().all(|cell| *cell == 0.0 || *cell == 1.0));
+
+        let mut env = SnakeEnv::new(EnvConfig {
+            observation: ObservationKind::Egocentric { radius: 3 },
+            ..EnvConfig::default()
+        });
+        let view = env.reset(0).remove(0);
+        assert_eq!((view.channels, view.height, view.width), (GRID_CHANNELS, 7, 7));
+        assert_eq!(view.data.len(), GRID_CHANNELS * 7 * 7);
+        assert_eq!(view.get(CHANNEL_OWN_HEAD, 3, 3), 1.0);
+        assert!(view.data.chunks(7 * 7).nth(CHANNEL_WALLS).unwrap().iter().all(|cell| *cell == 0.0));
+    }
+
+    #[test]
+    fn eating_food_is_rewarded() {
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        let direction = env.sim.snake_view(env.snakes[0]).unwrap().direction;
+        let ahead = env.heads()[0].unwrap().step(direction);
+        env.sim.spawn_food(ahead, FoodKind::Normal);
+
+        let (_, rewards, done, info) = env.step(&[direction]);
+        assert_eq!(env.heads()[0], Some(ahead));
+        assert!(rewards[0] > 0.0, "rewarded {}", rewards[0]);
+        assert!(!done);
+        assert_eq!(info.lengths[0], SpawnSettings::default().initial_tail + 2);
+    }
+
+    #[test]
+    fn running_into_a_snake_ends_the_episode() {
+        // Arenas wrap around rather than having walls, so the only thing to crash into is a snake
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        let direction = env.sim.snake_view(env.snakes[0]).unwrap().direction;
+        let ahead = env.heads()[0].unwrap().step(direction);
+        env.sim.spawn_player(ahead.step(direction), direction.opposite());
+
+        let (observations, rewards, done, info) = env.step(&[direction]);
+        assert!(done);
+        assert_eq!(rewards[0], RewardConfig::default().death);
+        assert_eq!(info.alive, vec![false]);
+        // A dead agent no longer sees itself
+        let own = &observations[0].data[..CHANNEL_OTHER_HEADS * ARENA_HEIGHT as usize * ARENA_WIDTH as usize];
+        assert!(own.iter().all(|cell| *cell == 0.0));
+    }
+}
@@ -1,16 +1,19 @@
+use std::cmp::Reverse;
 use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use iyes_loopless::prelude::*;
-use rand::random;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
 
 use crate::common::components::Position;
 use crate::common::components::Size;
 use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
-use crate::food::components::Food;
-use crate::snake::components::{SnakeHead, SnakeState};
-use crate::snake::spawn_tail;
+use crate::common::rng::GameRng;
+use crate::food::components::{Food, FoodKind, FoodSettings};
+use crate::snake::components::{Dying, SnakeHead, SnakeState};
+use crate::snake::{spawn_tail, SNAKE_TICK};
 use crate::state::GameState;
 
 pub mod components;
@@ -19,57 +22,144 @@ pub struct FoodPlugin;
 
 impl Plugin for FoodPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(eat_food.run_in_state(GameState::Running).after(SnakeState::Movement))
+        app.init_resource::<GameRng>()
+            .init_resource::<FoodSettings>()
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                2,
+                eat_food.run_in_state(GameState::Running).label(SnakeState::Eating),
+            )
+            .add_fixed_timestep_system(SNAKE_TICK, 1, expire_food.run_in_state(GameState::Running))
             .add_fixed_timestep(Duration::from_secs(1), "spawn_food")
             .add_fixed_timestep_system("spawn_food", 0, spawn_food.run_in_state(GameState::Running));
     }
 }
 
-const FOOD_COLOR: Color = Color::rgb(1.0, 0.0, 1.0);
+fn food_color(kind: FoodKind) -> Color {
+    match kind {
+        FoodKind::Normal => Color::rgb(1.0, 0.0, 1.0),
+        FoodKind::Golden => Color::GOLD,
+        FoodKind::Shrink => Color::rgb(0.3, 0.6, 1.0),
+        FoodKind::Speed => Color::rgb(1.0, 0.5, 0.0),
+        FoodKind::Ghost => Color::rgba(0.9, 0.9, 1.0, 0.5),
+        FoodKind::Poison => Color::rgb(0.2, 0.8, 0.1),
+    }
+}
 
-fn spawn_food(mut commands: Commands) {
+fn spawn_food(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    settings: Res<FoodSettings>,
+    foods: Query<(), With<Food>>,
+) {
+    if settings.room(foods.iter().count()) == 0 {
+        return;
+    }
+    let position = Position {
+        x: rng.0.gen_range(0..ARENA_WIDTH as i32),
+        y: rng.0.gen_range(0..ARENA_HEIGHT as i32),
+    };
+    if let Ok(weights) = WeightedIndex::new(settings.kinds.iter().map(|kind| kind.weight)) {
+        let kind = &settings.kinds[weights.sample(&mut rng.0)];
+        spawn_food_at(&mut commands, position, kind.kind, kind.lifetime);
+    }
+}
+
+pub fn spawn_food_at(commands: &mut Commands, position: Position, kind: FoodKind, lifetime: Option<u32>) -> Entity {
     commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
-                color: FOOD_COLOR,
+                color: food_color(kind),
                 ..default()
             },
             ..default()
         })
-        .insert(Food)
-        .insert(Position {
-            x: (random::<f32>() * ARENA_WIDTH as f32) as i32,
-            y: (random::<f32>() * ARENA_HEIGHT as f32) as i32,
+        .insert(Food {
+            kind,
+            expires_in: lifetime,
         })
-        .insert(Size::square(0.8));
+        .insert(position)
+        .insert(Size::square(0.8))
+        .id()
 }
 
+/// Feeds the snakes that survived the tick. Heads that share a cell and both survived, like teammates or an
+/// invulnerable snake, leave the food to the longer snake, or the one spawned first if they're as long.
 fn eat_food(
     mut commands: Commands,
-    foods: Query<(Entity, &Position), With<Food>>,
-    mut snakes: Query<(&Position, &mut SnakeHead)>,
+    settings: Res<FoodSettings>,
+    foods: Query<(Entity, &Position, &Food)>,
+    mut snakes: Query<(Entity, &Position, &mut SnakeHead), Without<Dying>>,
     positions: Query<&Position, (Without<SnakeHead>, Without<Food>)>,
 ) {
-    let food_positions = get_food_positions(foods);
+    let mut food_positions = get_food_positions(&foods);
+
+    let mut snakes: Vec<_> = snakes.iter_mut().collect();
+    snakes.sort_by_key(|(snake, _, head)| (Reverse(head.tail.len()), *snake));
+    for (_, position, mut head) in snakes {
+        // Food can be dropped onto food, and a snake eats all of it at once
+        for (entity, kind) in food_positions.remove(position).into_iter().flatten() {
+            commands.entity(entity).despawn();
+            match kind {
+                FoodKind::Normal => grow(&mut commands, &mut head, position, &positions, 1),
+                FoodKind::Golden => grow(&mut commands, &mut head, position, &positions, settings.golden_segments),
+                FoodKind::Shrink => shrink(&mut commands, &mut head, settings.shrink_segments),
+                FoodKind::Speed => head.speed_ticks = settings.effect_ticks,
+                FoodKind::Ghost => head.ghost_ticks = settings.effect_ticks,
+                FoodKind::Poison => {
+                    let segments = head.tail.len().div_ceil(2);
+                    shrink(&mut commands, &mut head, segments)
+                }
+            }
+        }
+    }
+}
+
+/// Adds `segments` tail segments at the tip of the tail, where they unfold from as the snake moves.
+fn grow(
+    commands: &mut Commands,
+    head: &mut SnakeHead,
+    head_position: &Position,
+    positions: &Query<&Position, (Without<SnakeHead>, Without<Food>)>,
+    segments: usize,
+) {
+    let position = match head.tail.last() {
+        Some(tip) => *positions.get(*tip).unwrap(),
+        None => *head_position,
+    };
+    for _ in 0..segments {
+        head.tail.push(spawn_tail(commands, position));
+    }
+}
+
+/// Removes up to `segments` tail segments from the tip of the tail.
+fn shrink(commands: &mut Commands, head: &mut SnakeHead, segments: usize) {
+    for _ in 0..segments {
+        match head.tail.pop() {
+            Some(tip) => commands.entity(tip).despawn(),
+            None => break,
+        }
+    }
+}
 
-    for (position, mut head) in snakes.iter_mut() {
-        if let Some(entity) = food_positions.get(position) {
-            commands.entity(*entity).despawn();
-            let mut position = position;
-            if !head.tail.is_empty() {
-                position = positions.get(*head.tail.last().unwrap()).unwrap();
+/// Despawns food whose lifetime ran out.
+fn expire_food(mut commands: Commands, mut foods: Query<(Entity, &mut Food)>) {
+    for (entity, mut food) in foods.iter_mut() {
+        if let Some(ticks) = &mut food.expires_in {
+            if *ticks == 0 {
+                commands.entity(entity).despawn();
+            } else {
+                *ticks -= 1;
             }
-            head.tail.push(spawn_tail(&mut commands, *position));
         }
     }
 }
 
 #[inline]
-fn get_food_positions(foods: Query<(Entity, &Position), With<Food>>) -> HashMap<Position, Entity> {
-    let mut food_positions = HashMap::new();
-    // Assumes no position has multiple food
-    for (entity, position) in foods.iter() {
-        food_positions.insert(*position, entity);
+fn get_food_positions(foods: &Query<(Entity, &Position, &Food)>) -> HashMap<Position, Vec<(Entity, FoodKind)>> {
+    let mut food_positions: HashMap<Position, Vec<(Entity, FoodKind)>> = HashMap::new();
+    for (entity, position, food) in foods.iter() {
+        food_positions.entry(*position).or_default().push((entity, food.kind));
     }
     food_positions
 }
@@ -1,4 +1,79 @@
 use bevy::prelude::Component;
 
 #[derive(Component)]
-pub struct Food;
+pub struct Food {
+    pub kind: FoodKind,
+    /// Ticks until the food despawns if nobody eats it, or `None` to last forever
+    pub expires_in: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FoodKind {
+    /// Grows the snake by a segment
+    Normal,
+    /// Grows the snake by several segments
+    Golden,
+    /// Takes segments off the snake
+    Shrink,
+    /// Speeds the snake up for a while
+    Speed,
+    /// Lets the snake pass through tails for a while
+    Ghost,
+    /// Cuts the snake's tail in half
+    Poison,
+}
+
+impl FoodKind {
+    /// Whether a snake is better off for eating this food.
+    pub fn is_beneficial(self) -> bool {
+        !matches!(self, FoodKind::Shrink | FoodKind::Poison)
+    }
+}
+
+/// Spawn chance and lifetime of one kind of food.
+pub struct FoodKindSettings {
+    pub kind: FoodKind,
+    /// Relative to the weights of the other kinds
+    pub weight: u32,
+    /// Ticks until the food despawns if nobody eats it, or `None` to last forever
+    pub lifetime: Option<u32>,
+}
+
+/// Which food spawns and what it does, set per game mode.
+pub struct FoodSettings {
+    /// Kinds that aren't listed never spawn
+    pub kinds: Vec<FoodKindSettings>,
+    pub golden_segments: usize,
+    pub shrink_segments: usize,
+    /// Ticks that speed and ghost effects last
+    pub effect_ticks: u32,
+    /// Most food that can be on the board at once, including food dropped by dead and boosting snakes
+    pub max_food: Option<usize>,
+}
+
+impl FoodSettings {
+    /// How much more food fits on the board, given `current` pieces of food.
+    pub fn room(&self, current: usize) -> usize {
+        self.max_food.map_or(usize::MAX, |max| max.saturating_sub(current))
+    }
+}
+
+impl Default for FoodSettings {
+    fn default() -> Self {
+        let kind = |kind, weight, lifetime| FoodKindSettings { kind, weight, lifetime };
+        Self {
+            kinds: vec![
+                kind(FoodKind::Normal, 70, None),
+                kind(FoodKind::Golden, 5, Some(100)),
+                kind(FoodKind::Shrink, 8, Some(200)),
+                kind(FoodKind::Speed, 7, Some(200)),
+                kind(FoodKind::Ghost, 5, Some(160)),
+                kind(FoodKind::Poison, 5, Some(300)),
+            ],
+            golden_segments: 3,
+            shrink_segments: 2,
+            effect_ticks: 100,
+            max_food: Some(100),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+pub mod ai;
+pub mod camera;
+pub mod cli;
+pub mod common;
+pub mod config;
+pub mod env;
+pub mod food;
+pub mod mode;
+pub mod net;
+pub mod player;
+pub mod simulation;
+pub mod snake;
+pub mod state;
+pub mod theme;
+pub mod tournament;
+pub mod ui;
+
+// Test
+pub mod client;
+pub mod server;
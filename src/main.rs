@@ -1,40 +1,98 @@
-use bevy::prelude::*;
-
-mod common;
-mod food;
-mod snake;
-mod state;
-mod ui;
+use std::time::Duration;
 
-// Test
-mod client;
-mod server;
+use bevy::prelude::*;
+use clap::Parser;
 
+use snakegame::ai::components::BotSettings;
+use snakegame::camera::components::{CameraSettings, MAX_ZOOM, MIN_ZOOM};
+use snakegame::common::conditioner::LinkConditions;
+use snakegame::common::rng::GameRng;
+use snakegame::common::transport::StreamLimits;
+use snakegame::config::UserConfig;
+use snakegame::mode::components::ModeSettings;
+use snakegame::net::NetRuntime;
+use snakegame::player::components::{Identity, LocalIdentity, PALETTE};
+use snakegame::player::parse_color;
+use snakegame::server::bot_api::ExternalBots;
+use snakegame::{
+    ai, camera, cli, client, common, config, food, mode, net, player, server, snake, theme, tournament, ui,
+};
 
-#[tokio::main]
-async fn main() {
-    let server = tokio::spawn(async {
-        server::server::run().await.unwrap();
-    });
-    let client = tokio::spawn(async {
-        client::client::run().await.unwrap();
-    });
+fn main() {
+    let cli = cli::Cli::parse();
+    if let Some(cli::Command::Tournament(args)) = &cli.command {
+        if let Err(e) = tournament::run(args) {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    //client::client::run();
-    App::new()
-        .insert_resource(WindowDescriptor {
-            title: "Snake!".to_string(),
-            width: 1000.0,
-            height: 1000.0,
-            // TODO: always opens on primary monitor, can't find the Current monitor for some reason
-            position: WindowPosition::Centered(MonitorSelection::Primary),
-            ..default()
-        })
+    let config = UserConfig::load();
+    let mode_settings = ModeSettings {
+        mode: cli.mode,
+        minutes: cli.match_minutes,
+        rounds: cli.rounds.max(1),
+        teams: cli.teams.max(1),
+        friendly_fire: cli.friendly_fire,
+        respawn_seconds: cli.respawn_seconds,
+        invulnerable_seconds: cli.invulnerable_seconds,
+    };
+    let (bots_tx, bots_rx) = crossbeam_channel::unbounded();
+    let mut app = App::new();
+    app.insert_resource(config.window_descriptor())
+        .insert_resource(config.keys.clone())
         .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
+        .insert_resource(BotSettings {
+            count: cli.bots.min(ai::MAX_BOTS),
+            strategy: cli.bot_strategy,
+        })
+        .insert_resource(mode_settings)
+        .insert_resource(LocalIdentity(Identity {
+            name: cli.name.clone().unwrap_or_else(|| config.name.clone()),
+            color: cli.color.or_else(|| parse_color(&config.color).ok()).unwrap_or(PALETTE[0]),
+            skin: cli.skin,
+        }))
+        .insert_resource(cli.theme)
+        .insert_resource(CameraSettings {
+            mode: cli.camera,
+            zoom: cli.zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+        })
+        .insert_resource(ExternalBots(bots_rx))
+        .insert_resource(cli.seed.map_or_else(GameRng::default, GameRng::from_seed))
+        .insert_resource(config)
+        .insert_resource(StreamLimits {
+            bidi: cli.max_bidi_streams,
+            uni: cli.max_uni_streams,
+        })
+        .insert_resource(LinkConditions {
+            seed: cli.seed,
+            ..cli.network.conditions()
+        })
         .add_plugins(DefaultPlugins)
+        .add_plugin(net::NetPlugin)
+        .add_plugin(config::ConfigPlugin)
         .add_plugin(ui::UiPlugin)
         .add_plugin(common::CommonPlugin)
-        .add_plugin(food::FoodPlugin)
         .add_plugin(snake::SnakePlugin)
-        .run();
+        .add_plugin(food::FoodPlugin)
+        .add_plugin(ai::AiPlugin)
+        .add_plugin(mode::ModePlugin)
+        .add_plugin(player::PlayerPlugin)
+        .add_plugin(theme::ThemePlugin)
+        .add_plugin(camera::CameraPlugin)
+        .add_plugin(server::bot_api::BotApiPlugin)
+        .add_plugin(server::server::ServerPlugin)
+        .add_plugin(client::browser::BrowserPlugin);
+
+    if let Some(addr) = cli.bot_api {
+        let deadline = Duration::from_millis(cli.bot_deadline_ms);
+        app.world.resource::<NetRuntime>().0.spawn(async move {
+            // Like a server that can't start, the game goes on without it
+            if let Err(e) = server::bot_api::listen(addr, deadline, bots_tx).await {
+                println!("[bot api] not accepting bots on {}: {}", addr, e);
+            }
+        });
+    }
+    app.run();
 }
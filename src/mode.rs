@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use iyes_loopless::prelude::*;
+
+use crate::ai::components::AiController;
+use crate::common::components::Position;
+use crate::common::rng::GameRng;
+use crate::food::components::{Food, FoodKind, FoodSettings};
+use crate::mode::components::{
+    GameMode, HighScore, MatchResults, MatchState, ModeSettings, Player, Respawn, ServerClock, Standing, Team,
+};
+use crate::player::components::Identity;
+use crate::snake::components::{
+    CollisionSettings, Dying, HeadOnRule, Invulnerable, SnakeHead, SnakeState, SpawnSettings, SpeedSettings, Tail,
+};
+use crate::snake::{safe_spawn_point, spawn_snake, SNAKE_TICK, TICK_SECONDS};
+use crate::state::GameState;
+
+pub mod components;
+
+pub struct ModePlugin;
+
+// Must be added after the SnakePlugin, whose `SNAKE_TICK` timestep it keeps score on
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ModeSettings>()
+            .init_resource::<MatchState>()
+            .init_resource::<HighScore>()
+            .init_resource::<ServerClock>()
+            .add_exit_system(GameState::MainMenu, start_match)
+            .add_enter_system(GameState::PreGame, clear_board)
+            .add_enter_system(GameState::PreGame, start_countdown)
+            .add_system(end_countdown.run_in_state(GameState::PreGame))
+            .add_enter_system(GameState::Intermission, start_intermission)
+            .add_system(end_intermission.run_in_state(GameState::Intermission))
+            .add_enter_system(GameState::MainMenu, clear_board)
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                2,
+                update_match.run_in_state(GameState::Running).after(SnakeState::Eating),
+            )
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                2,
+                // Takes the brains of dying snakes before they're removed
+                queue_respawns.run_in_state(GameState::Running).before(SnakeState::Removal),
+            )
+            .add_fixed_timestep_system(SNAKE_TICK, 2, respawn_players.run_in_state(GameState::Running));
+    }
+}
+
+/// Seconds counted down before each round.
+pub const COUNTDOWN_SECONDS: f64 = 3.0;
+/// Seconds the scoreboard is shown for between rounds.
+pub const INTERMISSION_SECONDS: f64 = 5.0;
+
+fn start_countdown(time: Res<Time>, clock: Res<ServerClock>, mut state: ResMut<MatchState>) {
+    state.phase_ends_at = clock.phase_end(&time, COUNTDOWN_SECONDS);
+}
+
+/// Lets the snakes go once the countdown is over.
+fn end_countdown(mut commands: Commands, time: Res<Time>, clock: Res<ServerClock>, state: Res<MatchState>) {
+    if clock.now(&time) >= state.phase_ends_at {
+        commands.insert_resource(NextState(GameState::Running));
+    }
+}
+
+fn start_intermission(time: Res<Time>, clock: Res<ServerClock>, mut state: ResMut<MatchState>) {
+    state.phase_ends_at = clock.phase_end(&time, INTERMISSION_SECONDS);
+}
+
+/// Moves on to the next round once the intermission is over.
+fn end_intermission(mut commands: Commands, time: Res<Time>, clock: Res<ServerClock>, mut state: ResMut<MatchState>) {
+    if clock.now(&time) >= state.phase_ends_at {
+        state.round += 1;
+        state.tick = 0;
+        commands.insert_resource(NextState(GameState::PreGame));
+    }
+}
+
+/// Sets up the rules of the chosen mode and starts keeping score.
+fn start_match(
+    mut commands: Commands,
+    settings: Res<ModeSettings>,
+    mut speed: ResMut<SpeedSettings>,
+    mut food: ResMut<FoodSettings>,
+    mut collisions: ResMut<CollisionSettings>,
+    mut spawns: ResMut<SpawnSettings>,
+) {
+    *speed = SpeedSettings::default();
+    *food = FoodSettings::default();
+    *collisions = CollisionSettings::default();
+    // Classic starts from a lone head like it always has, other modes give snakes a head start to fight with
+    *spawns = SpawnSettings {
+        initial_tail: if settings.mode == GameMode::Classic { 0 } else { 2 },
+        ..default()
+    };
+    match settings.mode {
+        GameMode::Classic | GameMode::FreeForAll => {}
+        GameMode::Timed => {
+            // Racing for length, so more food and bumping heads rewards the bigger snake
+            food.max_food = Some(150);
+            collisions.head_on = HeadOnRule::LongerWins;
+        }
+        GameMode::LastStanding => {
+            // Ghosts can't be cornered, which drags rounds out
+            food.kinds.retain(|kind| kind.kind != FoodKind::Ghost);
+        }
+        GameMode::Team => {
+            collisions.head_on = HeadOnRule::LongerWins;
+            collisions.friendly_fire = settings.friendly_fire;
+        }
+    }
+    commands.insert_resource(MatchState { round: 1, ..default() });
+    info!("Starting {} match", settings.mode);
+}
+
+/// Keeps score as the match plays out, and ends the round or match once the mode's win condition is met.
+fn update_match(
+    mut commands: Commands,
+    settings: Res<ModeSettings>,
+    mut state: ResMut<MatchState>,
+    mut high_score: ResMut<HighScore>,
+    snakes: Query<(Entity, &Player, &SnakeHead), Without<Dying>>,
+    humans: Query<&Player, (Without<AiController>, Without<Dying>)>,
+    details: Query<(Option<&Team>, Option<&Identity>)>,
+) {
+    state.tick += 1;
+    for (snake, player, head) in snakes.iter() {
+        state.lengths.insert(*player, head.tail.len() + 1);
+        let (team, identity) = details.get(snake).unwrap();
+        if let Some(identity) = identity {
+            state.identities.entry(*player).or_insert_with(|| identity.clone());
+        }
+        if let Some(team) = team {
+            state.teams.insert(*player, *team);
+        }
+    }
+
+    let players = state.lengths.len();
+    let alive: Vec<Player> = snakes.iter().map(|(_, player, _)| *player).collect();
+    let over = match settings.mode {
+        GameMode::Classic => humans.is_empty(),
+        GameMode::Timed => alive.is_empty() || state.tick as f32 * TICK_SECONDS >= settings.minutes as f32 * 60.0,
+        // A lone snake plays until it dies, otherwise the round ends once one snake is left
+        GameMode::LastStanding => alive.len() <= if players > 1 { 1 } else { 0 },
+        GameMode::FreeForAll => false,
+        GameMode::Team => {
+            let teams: HashSet<Team> =
+                state.teams.iter().filter(|(player, _)| alive.contains(player)).map(|(_, team)| *team).collect();
+            let all_teams: HashSet<&Team> = state.teams.values().collect();
+            teams.len() <= if all_teams.len() > 1 { 1 } else { 0 }
+        }
+    };
+    if !over {
+        return;
+    }
+
+    if settings.mode == GameMode::LastStanding {
+        if let [winner] = alive[..] {
+            *state.round_wins.entry(winner).or_default() += 1;
+        }
+        if state.round < settings.rounds {
+            info!("Round {} of {} over", state.round, settings.rounds);
+            commands.insert_resource(results(settings.mode, &state));
+            commands.insert_resource(NextState(GameState::Intermission));
+            return;
+        }
+    }
+
+    let results = results(settings.mode, &state);
+    if settings.mode == GameMode::Classic {
+        high_score.0 = high_score.0.max(state.lengths.get(&Player(0)).copied().unwrap_or(0));
+    }
+    info!(
+        "{} match over, winner: {}",
+        results.mode,
+        results.winner.as_deref().unwrap_or("draw")
+    );
+    for standing in &results.standings {
+        info!("  {}: {}", standing.name, standing.score);
+    }
+    commands.insert_resource(results);
+    commands.insert_resource(NextState(GameState::Results));
+}
+
+/// Puts players whose snake died in free-for-all in line to respawn after the delay, along with the brain of
+/// a bot's snake.
+fn queue_respawns(
+    mut commands: Commands,
+    settings: Res<ModeSettings>,
+    state: Res<MatchState>,
+    dying: Query<(Entity, &Player, Option<&Team>), With<Dying>>,
+) {
+    if settings.mode != GameMode::FreeForAll {
+        return;
+    }
+    let at = state.tick + (settings.respawn_seconds / TICK_SECONDS) as u64;
+    for (snake, player, team) in dying.iter() {
+        let (player, team) = (*player, team.copied());
+        commands.add(move |world: &mut World| {
+            let brain = world.entity_mut(snake).remove::<AiController>().map(|ai| ai.brain);
+            world.resource_mut::<MatchState>().respawns.push(Respawn {
+                player,
+                team,
+                at,
+                brain,
+            });
+        });
+    }
+}
+
+/// Brings dead players back in free-for-all once the respawn delay is up, away from the other snakes and
+/// invulnerable for a while.
+fn respawn_players(
+    mut commands: Commands,
+    settings: Res<ModeSettings>,
+    mut rng: ResMut<GameRng>,
+    spawns: Res<SpawnSettings>,
+    mut state: ResMut<MatchState>,
+    // Every head and tail segment
+    snakes: Query<&Position, Without<Food>>,
+) {
+    if settings.mode != GameMode::FreeForAll {
+        return;
+    }
+    let mut occupied: HashSet<Position> = snakes.iter().copied().collect();
+    let tick = state.tick;
+    let mut waiting = vec![];
+    for respawn in std::mem::take(&mut state.respawns) {
+        let spawn = if respawn.at <= tick { safe_spawn_point(&occupied, &mut rng.0) } else { None };
+        let (position, direction) = match spawn {
+            Some(spawn) => spawn,
+            None => {
+                waiting.push(respawn);
+                continue;
+            }
+        };
+        let Respawn {
+            player, team, brain, ..
+        } = respawn;
+        occupied.insert(position);
+        let snake = spawn_snake(&mut commands, position, direction, spawns.initial_tail);
+        commands.entity(snake).insert(player).insert(Invulnerable {
+            ticks: (settings.invulnerable_seconds / TICK_SECONDS) as u32,
+        });
+        if let Some(team) = team {
+            commands.entity(snake).insert(team);
+        }
+        if let Some(identity) = state.identities.get(&player) {
+            commands.entity(snake).insert(identity.clone());
+        }
+        if let Some(brain) = brain {
+            commands.entity(snake).insert(AiController { brain });
+        }
+        info!("{} respawned", player);
+    }
+    state.respawns = waiting;
+}
+
+/// Removes what's left of the last round, once the snakes that died at the end of it have been removed.
+fn clear_board(
+    mut commands: Commands,
+    heads: Query<Entity, With<SnakeHead>>,
+    tails: Query<Entity, With<Tail>>,
+    foods: Query<Entity, With<Food>>,
+) {
+    for entity in heads.iter().chain(tails.iter()).chain(foods.iter()) {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Final standings of a match, scoring rounds won in last snake standing, team lengths in team matches and
+/// snake lengths otherwise.
+fn results(mode: GameMode, state: &MatchState) -> MatchResults {
+    let mut scores: HashMap<String, usize> = HashMap::new();
+    let mut players: Vec<&Player> = state.lengths.keys().collect();
+    players.sort();
+    for player in players {
+        let name = state.identities.get(player).map_or_else(|| player.to_string(), |id| id.name.clone());
+        let (name, score) = match mode {
+            GameMode::Classic | GameMode::Timed | GameMode::FreeForAll => (name, state.lengths[player]),
+            GameMode::LastStanding => (name, state.round_wins.get(player).copied().unwrap_or(0) as usize),
+            GameMode::Team => match state.teams.get(player) {
+                Some(team) => (format!("Team {}", team.0 + 1), state.lengths[player]),
+                None => (name, state.lengths[player]),
+            },
+        };
+        *scores.entry(name).or_default() += score;
+    }
+
+    let mut standings: Vec<Standing> = scores.into_iter().map(|(name, score)| Standing { name, score }).collect();
+    standings.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    let winner = match &standings[..] {
+        [only] => Some(only.name.clone()),
+        [first, second, ..] if first.score > second.score => Some(first.name.clone()),
+        _ => None,
+    };
+    MatchResults {
+        mode,
+        standings,
+        winner,
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::{Component, Time};
+use bevy::utils::HashMap;
+
+use crate::ai::brain::SnakeBrain;
+use crate::player::components::Identity;
+
+/// Rules and win condition of a match, chosen in the lobby or on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Single-player high score, over once the player's snake dies
+    Classic,
+    /// The longest snake when time runs out wins
+    Timed,
+    /// Rounds that end with one snake left, won by whoever wins the most rounds
+    LastStanding,
+    /// Snakes play in teams, which score their members' lengths together
+    Team,
+    /// Endless drop-in game where dead snakes respawn after a delay
+    FreeForAll,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::Timed,
+        GameMode::LastStanding,
+        GameMode::Team,
+        GameMode::FreeForAll,
+    ];
+
+    /// The mode after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|m| *m == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GameMode::Classic => "classic",
+            GameMode::Timed => "timed",
+            GameMode::LastStanding => "last-standing",
+            GameMode::Team => "team",
+            GameMode::FreeForAll => "free-for-all",
+        })
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|mode| mode.to_string() == s.to_lowercase()).ok_or_else(|| {
+            format!(
+                "unknown mode '{}', expected one of classic, timed, last-standing, team, free-for-all",
+                s
+            )
+        })
+    }
+}
+
+/// The mode of the next match and its options.
+pub struct ModeSettings {
+    pub mode: GameMode,
+    /// Length of a timed match
+    pub minutes: u64,
+    /// Rounds in a last snake standing match
+    pub rounds: u32,
+    /// Teams in a team match, which snakes are dealt into in turn
+    pub teams: usize,
+    /// Whether teammates die running into each other in a team match
+    pub friendly_fire: bool,
+    /// Delay before a dead snake respawns in free-for-all
+    pub respawn_seconds: f32,
+    /// How long a respawned snake can't die for
+    pub invulnerable_seconds: f32,
+}
+
+impl Default for ModeSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Classic,
+            minutes: 3,
+            rounds: 3,
+            teams: 2,
+            friendly_fire: false,
+            respawn_seconds: 3.0,
+            invulnerable_seconds: 3.0,
+        }
+    }
+}
+
+/// Which of the match's players a snake belongs to, so scores carry over when snakes are replaced between
+/// rounds. Player 0 is the local player and the rest are bots.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Player(pub usize);
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("Player"),
+            n => write!(f, "Bot {}", n),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Team(pub usize);
+
+/// Progress of the match being played.
+#[derive(Default)]
+pub struct MatchState {
+    /// Ticks played in the current round
+    pub tick: u64,
+    /// Starting from 1
+    pub round: u32,
+    /// Length of each player's snake, as of its last tick alive
+    pub lengths: HashMap<Player, usize>,
+    pub teams: HashMap<Player, Team>,
+    pub identities: HashMap<Player, Identity>,
+    pub round_wins: HashMap<Player, u32>,
+    /// When the current countdown or intermission ends, in [`ServerClock`] time
+    pub phase_ends_at: f64,
+    /// Dead players waiting to respawn
+    pub respawns: Vec<Respawn>,
+}
+
+/// A dead player waiting to respawn in free-for-all.
+pub struct Respawn {
+    pub player: Player,
+    pub team: Option<Team>,
+    /// Tick to respawn on
+    pub at: u64,
+    /// What steered the dead snake, if it wasn't steered by hand, so a bot comes back as the same bot
+    pub brain: Option<Box<dyn SnakeBrain>>,
+}
+
+/// Clock that countdowns and intermissions are scheduled on. In a networked game every client offsets its
+/// own clock to the server's, so they all start rounds at the same moment.
+#[derive(Default)]
+pub struct ServerClock {
+    /// Seconds to add to the local time since startup to get the server's
+    pub offset: f64,
+    /// End of the latest countdown or intermission the server joined announced, which the one started here
+    /// keeps to rather than timing its own
+    pub phase_ends_at: Option<f64>,
+}
+
+impl ServerClock {
+    pub fn now(&self, time: &Time) -> f64 {
+        time.seconds_since_startup() + self.offset
+    }
+
+    /// When a phase of `seconds` starting now ends, or the server's end for it if it announced one.
+    pub fn phase_end(&self, time: &Time, seconds: f64) -> f64 {
+        let now = self.now(time);
+        self.phase_ends_at.filter(|ends_at| *ends_at > now).unwrap_or(now + seconds)
+    }
+}
+
+pub struct Standing {
+    pub name: String,
+    pub score: usize,
+}
+
+/// Standings of the match being played as of the last round, or its outcome once it's over.
+pub struct MatchResults {
+    pub mode: GameMode,
+    /// Best score first
+    pub standings: Vec<Standing>,
+    /// `None` when the best score is shared
+    pub winner: Option<String>,
+}
+
+/// Best length reached in a classic match since the game started.
+#[derive(Default)]
+pub struct HighScore(pub usize);
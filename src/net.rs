@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::client::browser::ServerBrowser;
+use crate::common::conditioner::LinkConditions;
+use crate::common::transport::{Link, StreamLimits};
+use crate::net::components::*;
+use crate::server::server::ServerHandle;
+
+pub mod components;
+
+/// How long the game waits on exit for the goodbyes of its connections to get out.
+pub const SHUTDOWN_LINGER: Duration = Duration::from_millis(100);
+
+/// The Tokio runtime that networking runs on, for systems to start network tasks on. Owned by [`NetPlugin`], so
+/// it lives as long as the game does.
+pub struct NetRuntime(pub Runtime);
+
+/// Runs networking alongside the game: the server and clients talk to it through channels on their own tasks,
+/// and it hands what they hear to systems as events, and what systems send to them.
+///
+/// Must be added before the plugins that network.
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        let runtime = Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("net")
+            .build()
+            .expect("couldn't start the networking runtime");
+        app.insert_resource(NetRuntime(runtime))
+            .init_resource::<StreamLimits>()
+            .init_resource::<LinkConditions>()
+            .add_event::<NetMessageReceived>()
+            .add_event::<StateReceived>()
+            .add_event::<PlayerConnected>()
+            .add_event::<PlayerDisconnected>()
+            .add_event::<SendNetMessage>()
+            .add_event::<SendState>()
+            .add_system_to_stage(CoreStage::PostUpdate, send_messages)
+            .add_system_to_stage(CoreStage::Last, linger_on_exit.after(CloseConnections));
+    }
+}
+
+/// Hands the messages and states systems sent this frame to the links they go to.
+fn send_messages(
+    mut messages: EventReader<SendNetMessage>,
+    mut states: EventReader<SendState>,
+    server: Option<Res<ServerHandle>>,
+    browser: Res<ServerBrowser>,
+) {
+    let links = |to: Recipient| -> Vec<Link> {
+        match to {
+            Recipient::Server => browser.link.iter().cloned().collect(),
+            Recipient::Client(slot) => server.iter().filter_map(|server| server.link(slot)).collect(),
+            Recipient::Clients => server.iter().flat_map(|server| server.links()).collect(),
+        }
+    };
+    for SendNetMessage { to, message } in messages.iter() {
+        for link in links(*to) {
+            link.send(message.clone());
+        }
+    }
+    for SendState { to, state } in states.iter() {
+        for link in links(*to) {
+            link.send_state(state.clone());
+        }
+    }
+}
+
+/// Gives the connections closed on exit a moment to say goodbye before the game, and the runtime, are gone.
+fn linger_on_exit(mut exits: EventReader<AppExit>) {
+    if exits.iter().last().is_some() {
+        std::thread::sleep(SHUTDOWN_LINGER);
+    }
+}
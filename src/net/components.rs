@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+
+use crate::common::protocol::{DisconnectReason, NetMessage, StateUpdate};
+
+/// The other end of a connection a message came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Peer {
+    /// The server this game joined
+    Server,
+    /// A client of this game's server, by its player slot
+    Client(usize),
+}
+
+/// Who a message goes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recipient {
+    /// The server this game joined, if any
+    Server,
+    /// A client of this game's server, by its player slot
+    Client(usize),
+    /// Every client connected to this game's server
+    Clients,
+}
+
+/// A message that came in from the other end of a connection.
+pub struct NetMessageReceived {
+    pub from: Peer,
+    pub message: NetMessage,
+}
+
+/// The newest state from the other end of a connection, of those that arrived.
+pub struct StateReceived {
+    pub from: Peer,
+    pub state: StateUpdate,
+}
+
+/// A client got into this game's server, either for the first time or back into the slot it lost.
+pub struct PlayerConnected {
+    pub slot: usize,
+    pub name: String,
+    pub resumed: bool,
+}
+
+/// A client of this game's server lost its connection or left.
+pub struct PlayerDisconnected {
+    pub slot: usize,
+    pub name: String,
+    pub reason: DisconnectReason,
+    /// Whether the slot is kept for the client to reconnect to
+    pub resumable: bool,
+}
+
+/// Sent by systems for the message to go out at the end of the frame, after those sent before it.
+pub struct SendNetMessage {
+    pub to: Recipient,
+    pub message: NetMessage,
+}
+
+/// Sent by systems for the state to go out at the end of the frame, unless a newer one overtakes it.
+pub struct SendState {
+    pub to: Recipient,
+    pub state: StateUpdate,
+}
+
+/// Label of the systems closing connections when the game exits, which get a moment for their goodbyes to get out
+/// before it does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct CloseConnections;
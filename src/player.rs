@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use iyes_loopless::prelude::*;
+
+use crate::camera::components::OVERLAY_LAYER;
+use crate::player::components::{Identity, LocalIdentity, NameTag, PALETTE};
+use crate::snake::components::SnakeHead;
+use crate::state::GameState;
+
+pub mod components;
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LocalIdentity>().add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            ConditionSet::new()
+                .run_not_in_state(GameState::MainMenu)
+                .with_system(paint_snakes)
+                .with_system(name_tags)
+                .into(),
+        );
+    }
+}
+
+/// `name`, or `name (2)`, `name (3)` and so on if another snake already goes by it.
+pub fn unique_name<'a>(name: &str, taken: impl Iterator<Item = &'a str> + Clone) -> String {
+    let name = name.trim();
+    let name = if name.is_empty() { "Player" } else { name };
+    (1..)
+        .map(|n| if n == 1 { name.to_string() } else { format!("{} ({})", name, n) })
+        .find(|candidate| !taken.clone().any(|taken| taken == candidate))
+        .unwrap()
+}
+
+/// First color of the palette nobody is using, so bots don't look like the player or each other.
+pub fn free_color<'a>(taken: impl Iterator<Item = &'a Color> + Clone) -> Color {
+    PALETTE.into_iter().find(|color| !taken.clone().any(|taken| taken == color)).unwrap_or(PALETTE[0])
+}
+
+/// The name and color asked for, changed to ones nobody in `taken` has where someone already does. A color that
+/// wasn't asked for is a free one.
+pub fn settle_identity(name: &str, color: Option<Color>, taken: &[(&str, &Color)]) -> (String, Color) {
+    let name = unique_name(name, taken.iter().map(|(name, _)| *name));
+    let colors = taken.iter().map(|(_, color)| *color);
+    let color = match color {
+        Some(color) if !colors.clone().any(|taken| *taken == color) => color,
+        _ => free_color(colors),
+    };
+    (name, color)
+}
+
+/// Parses a color written as hex, e.g. `#33cc4d` or `33cc4d`. Colors of the palette come back exactly, so a
+/// saved color is still recognised as one of them.
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let color = Color::hex(s.trim_start_matches('#'))
+        .map_err(|_| format!("invalid color '{}', expected hex like #33cc4d", s))?;
+    Ok(PALETTE.into_iter().find(|palette| color_hex(*palette) == color_hex(color)).unwrap_or(color))
+}
+
+/// Writes a color as hex, e.g. `#33cc4d`.
+pub fn color_hex(color: Color) -> String {
+    let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(color.r()), byte(color.g()), byte(color.b()))
+}
+
+/// Colors every snake's head and tail by its player's color and skin, in either theme.
+fn paint_snakes(
+    heads: Query<(Entity, &SnakeHead, &Identity)>,
+    mut sprites: Query<&mut Sprite>,
+    mut atlas_sprites: Query<&mut TextureAtlasSprite>,
+) {
+    for (snake, head, identity) in heads.iter() {
+        let segments = [None].into_iter().chain((0..head.tail.len()).map(Some));
+        for (entity, index) in [snake].iter().chain(&head.tail).zip(segments) {
+            let color = identity.segment_color(index, head.tail.len());
+            if let Ok(mut sprite) = sprites.get_mut(*entity) {
+                sprite.color = color;
+            }
+            if let Ok(mut sprite) = atlas_sprites.get_mut(*entity) {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+/// Keeps a name tag floating above every snake's head, removing it along with the snake.
+fn name_tags(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    // Only snake heads have an identity
+    heads: Query<(Entity, &Identity, &Transform, &Visibility), Without<NameTag>>,
+    mut tags: Query<(Entity, &NameTag, &mut Transform, &mut Visibility)>,
+) {
+    let mut tagged = vec![];
+    for (tag, NameTag(snake), mut transform, mut visibility) in tags.iter_mut() {
+        match heads.get(*snake) {
+            Ok((_, _, head, head_visibility)) => {
+                // Heads are scaled to the size of a cell
+                transform.translation = head.translation + Vec3::new(0.0, head.scale.y, 1.0);
+                visibility.is_visible = head_visibility.is_visible;
+                tagged.push(*snake);
+            }
+            Err(_) => commands.entity(tag).despawn(),
+        }
+    }
+
+    for (snake, identity, _, _) in heads.iter() {
+        if !tagged.contains(&snake) {
+            commands
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        identity.name.clone(),
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 20.0,
+                            color: identity.color,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    ..default()
+                })
+                .insert(RenderLayers::layer(OVERLAY_LAYER))
+                .insert(NameTag(snake));
+        }
+    }
+}
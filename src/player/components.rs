@@ -0,0 +1,107 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::{Color, Component, Entity};
+use serde::{Deserialize, Serialize};
+
+/// Colors handed out to snakes that don't pick their own, in order.
+pub const PALETTE: [Color; 8] = [
+    Color::rgb(0.2, 0.8, 0.3),
+    Color::rgb(0.9, 0.3, 0.3),
+    Color::rgb(0.3, 0.5, 1.0),
+    Color::rgb(1.0, 0.8, 0.2),
+    Color::rgb(0.8, 0.4, 1.0),
+    Color::rgb(0.2, 0.9, 0.9),
+    Color::rgb(1.0, 0.5, 0.1),
+    Color::rgb(0.9, 0.9, 0.9),
+];
+
+/// Pattern painted along a snake's tail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Skin {
+    Solid,
+    /// Every other segment darker
+    Stripes,
+    /// Fading out towards the tip of the tail
+    Gradient,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 3] = [Skin::Solid, Skin::Stripes, Skin::Gradient];
+
+    /// The skin after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|s| *s == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for Skin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Skin::Solid => "solid",
+            Skin::Stripes => "stripes",
+            Skin::Gradient => "gradient",
+        })
+    }
+}
+
+impl FromStr for Skin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|skin| skin.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown skin '{}', expected one of solid, stripes, gradient", s))
+    }
+}
+
+/// How a snake's player shows up to everyone else.
+#[derive(Component, Clone, Debug)]
+pub struct Identity {
+    /// Unique among the snakes in the game
+    pub name: String,
+    pub color: Color,
+    pub skin: Skin,
+}
+
+impl Identity {
+    /// Color of the head, or of tail segment `index` counting from the head out of `len`.
+    pub fn segment_color(&self, index: Option<usize>, len: usize) -> Color {
+        let index = match index {
+            Some(index) => index,
+            None => return self.color,
+        };
+        let shade = match self.skin {
+            Skin::Solid => 0.7,
+            Skin::Stripes => {
+                if index % 2 == 0 {
+                    0.7
+                } else {
+                    0.4
+                }
+            }
+            Skin::Gradient => 0.8 - 0.6 * (index + 1) as f32 / len.max(1) as f32,
+        };
+        Color::rgb(self.color.r() * shade, self.color.g() * shade, self.color.b() * shade)
+    }
+}
+
+/// The local player's identity, chosen in the lobby or on the command line.
+pub struct LocalIdentity(pub Identity);
+
+impl Default for LocalIdentity {
+    fn default() -> Self {
+        Self(Identity {
+            name: "Player".to_string(),
+            color: PALETTE[0],
+            skin: Skin::Solid,
+        })
+    }
+}
+
+/// Name shown above the head of `snake`.
+#[derive(Component)]
+pub struct NameTag(pub Entity);
@@ -0,0 +1,432 @@
+use std::iter;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use crossbeam_channel::{Receiver, Sender};
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::ai::board::{Board, SnakeView};
+use crate::ai::brain::SnakeBrain;
+use crate::ai::components::AiController;
+use crate::common::components::{Direction, Position};
+use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
+use crate::common::rng::GameRng;
+use crate::food::components::Food;
+use crate::mode::components::{MatchState, Player};
+use crate::player::components::{Identity, Skin};
+use crate::player::{parse_color, settle_identity};
+use crate::snake::components::{SnakeHead, SpawnSettings};
+use crate::snake::{safe_spawn_point, spawn_snake};
+use crate::state::GameState;
+
+// External bot protocol: newline delimited JSON over TCP, or over stdin/stdout for bot processes.
+//
+// 1. The bot connects and sends a `Hello`, e.g. `{"name":"my-bot","color":"#33cc4d","skin":"stripes"}`. Color
+//    and skin are optional. The name gets a number added, and the color is swapped for a free one, if another
+//    snake already has it
+// 2. At the end of every tick before the bot's snake moves, the server sends a `TickState`
+// 3. The bot answers with a `Reply` for that tick, e.g. `{"tick":12,"direction":"left"}`
+//
+// A reply that doesn't arrive within the deadline, or is for an older tick, is ignored and the snake
+// continues straight on. The game doesn't wait for replies, so one that arrives after the snake moved is
+// ignored too, while a headless match waits out the deadline for bot processes. The bot's snake is removed
+// from the game when the connection closes.
+
+#[derive(Deserialize)]
+pub struct Hello {
+    pub name: String,
+    /// Hex color, or a free color from the palette if missing or taken
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub skin: Option<Skin>,
+}
+
+#[derive(Serialize)]
+pub struct TickState<'a> {
+    pub tick: u64,
+    /// Index of the bot's own snake in `snakes`
+    pub you: usize,
+    pub width: u32,
+    pub height: u32,
+    pub snakes: &'a [SnakeView],
+    pub food: &'a [Position],
+}
+
+#[derive(Deserialize)]
+pub struct Reply {
+    pub tick: u64,
+    pub direction: Direction,
+}
+
+/// A bot that connected over the socket and is waiting for a snake.
+pub struct ExternalBot {
+    pub name: String,
+    pub color: Option<Color>,
+    pub skin: Skin,
+    pub brain: ExternalBrain,
+}
+
+/// Channel of newly connected external bots, fed by [`listen`].
+pub struct ExternalBots(pub Receiver<ExternalBot>);
+
+/// [`SnakeBrain`] that forwards each decision to an external bot.
+pub struct ExternalBrain {
+    name: String,
+    tick: u64,
+    deadline: Duration,
+    /// When the reply to the state just sent is due, if one was sent
+    answer_by: Option<Instant>,
+    /// Whether to wait for the reply when deciding, rather than only take a reply that's already in
+    waits: bool,
+    states: UnboundedSender<String>,
+    /// With when each reply came in
+    replies: Receiver<(Reply, Instant)>,
+}
+
+impl SnakeBrain for ExternalBrain {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.states.is_closed()
+    }
+
+    fn prepare(&mut self, board: &Board, me: usize, now: Instant) {
+        self.tick += 1;
+        let state = TickState {
+            tick: self.tick,
+            you: me,
+            width: ARENA_WIDTH,
+            height: ARENA_HEIGHT,
+            snakes: &board.snakes,
+            food: &board.food,
+        };
+        let sent = self.states.send(serde_json::to_string(&state).unwrap()).is_ok();
+        self.answer_by = sent.then(|| now + self.deadline);
+    }
+
+    fn decide(&mut self, board: &Board, me: usize) -> Direction {
+        let straight = board.snakes[me].direction;
+        let answer_by = match self.answer_by.take() {
+            Some(answer_by) => answer_by,
+            None => return straight,
+        };
+        // Late replies to earlier ticks are skipped
+        let tick = self.tick;
+        let answered = |(reply, at): &(Reply, Instant)| reply.tick == tick && *at <= answer_by;
+        let reply = if self.waits {
+            iter::from_fn(|| self.replies.recv_deadline(answer_by).ok()).find(answered)
+        } else {
+            self.replies.try_iter().filter(answered).last()
+        };
+        reply.map_or(straight, |(reply, _)| reply.direction)
+    }
+}
+
+pub struct BotApiPlugin;
+
+impl Plugin for BotApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_external_bots.run_in_state(GameState::Running))
+            .add_system(despawn_disconnected_bots.run_in_state(GameState::Running));
+    }
+}
+
+/// Accepts external bot connections on `addr`, handing each one to the game through `bots`.
+pub async fn listen(addr: SocketAddr, deadline: Duration, bots: Sender<ExternalBot>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    println!("[bot api] listening: addr={}", addr);
+    loop {
+        let (stream, remote) = listener.accept().await?;
+        let bots = bots.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_bot(stream, deadline, bots).await {
+                println!("[bot api] bot {} disconnected: {}", remote, e);
+            }
+        });
+    }
+}
+
+/// Starts a bot process with `command`, talking to it over its stdin and stdout. Its brain waits for each
+/// reply, for headless matches that have nothing else to hold up.
+pub fn spawn_process_bot(command: &str, deadline: Duration) -> std::io::Result<ExternalBrain> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let reader = BufReader::new(child.stdout.take().unwrap());
+    let writer = child.stdin.take().unwrap();
+    let (brain, link) = link(command.to_string(), deadline, true);
+
+    let command = command.to_string();
+    tokio::spawn(async move {
+        let mut lines = reader.lines();
+        let result = match read_hello(&mut lines).await {
+            Ok(Some(_)) => pump(lines, writer, link).await,
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            println!("[bot api] bot process '{}' failed: {}", command, e);
+        }
+        // Dropping the child kills the process if it's still running
+        drop(child);
+    });
+    Ok(brain)
+}
+
+/// Channels connecting an [`ExternalBrain`] to the task talking to its bot.
+struct BotLink {
+    states: UnboundedReceiver<String>,
+    replies: Sender<(Reply, Instant)>,
+}
+
+fn link(name: String, deadline: Duration, waits: bool) -> (ExternalBrain, BotLink) {
+    let (states_tx, states_rx) = unbounded_channel();
+    let (replies_tx, replies_rx) = crossbeam_channel::unbounded();
+    let brain = ExternalBrain {
+        name,
+        tick: 0,
+        deadline,
+        answer_by: None,
+        waits,
+        states: states_tx,
+        replies: replies_rx,
+    };
+    let link = BotLink {
+        states: states_rx,
+        replies: replies_tx,
+    };
+    (brain, link)
+}
+
+async fn read_hello<R: AsyncBufRead + Unpin>(lines: &mut Lines<R>) -> std::io::Result<Option<Hello>> {
+    match lines.next_line().await? {
+        Some(line) => Ok(Some(serde_json::from_str(&line)?)),
+        None => Ok(None),
+    }
+}
+
+async fn handle_bot(stream: TcpStream, deadline: Duration, bots: Sender<ExternalBot>) -> std::io::Result<()> {
+    let (reader, writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let hello = match read_hello(&mut lines).await? {
+        Some(hello) => hello,
+        None => return Ok(()),
+    };
+    println!("[bot api] bot connected: name={}", hello.name);
+
+    let color = match hello.color.as_deref().map(parse_color).transpose() {
+        Ok(color) => color,
+        Err(e) => {
+            println!("[bot api] ignoring color of bot {}: {}", hello.name, e);
+            None
+        }
+    };
+    let (brain, link) = link(hello.name.clone(), deadline, false);
+    if bots
+        .send(ExternalBot {
+            name: hello.name,
+            color,
+            skin: hello.skin.unwrap_or(Skin::Solid),
+            brain,
+        })
+        .is_err()
+    {
+        return Ok(());
+    }
+    pump(lines, writer, link).await
+}
+
+/// Forwards tick states to the bot and its replies back to the brain, until either side goes away.
+async fn pump<R, W>(mut lines: Lines<R>, mut writer: W, mut link: BotLink) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        tokio::select! {
+            state = link.states.recv() => match state {
+                Some(state) => {
+                    writer.write_all(state.as_bytes()).await?;
+                    writer.write_all(b"\n").await?;
+                    writer.flush().await?;
+                }
+                // The bot's snake is gone
+                None => return Ok(()),
+            },
+            line = lines.next_line() => match line? {
+                Some(line) => match serde_json::from_str::<Reply>(&line) {
+                    Ok(reply) => {
+                        let _ = link.replies.send((reply, Instant::now()));
+                    }
+                    Err(e) => println!("[bot api] ignoring malformed reply: {}", e),
+                },
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Gives each newly connected bot a snake, playing as the next player after everyone who's played so far.
+fn spawn_external_bots(
+    mut commands: Commands,
+    bots: Res<ExternalBots>,
+    spawns: Res<SpawnSettings>,
+    state: Res<MatchState>,
+    mut rng: ResMut<GameRng>,
+    // Every head and tail segment
+    snakes: Query<&Position, Without<Food>>,
+    players: Query<(&Player, &Identity)>,
+) {
+    let mut occupied: HashSet<Position> = snakes.iter().copied().collect();
+    // Dead players may come back, so they keep their names and colors too
+    let mut taken: Vec<Identity> =
+        players.iter().map(|(_, identity)| identity).chain(state.identities.values()).cloned().collect();
+    // Dead players keep their scores, so their slots stay taken
+    let mut next_player = players
+        .iter()
+        .map(|(player, _)| *player)
+        .chain(state.lengths.keys().copied())
+        .map(|player| player.0 + 1)
+        .max()
+        .unwrap_or(1);
+    for bot in bots.0.try_iter() {
+        if let Some((position, direction)) = safe_spawn_point(&occupied, &mut rng.0) {
+            let others: Vec<(&str, &Color)> = taken.iter().map(|id| (id.name.as_str(), &id.color)).collect();
+            let (name, color) = settle_identity(&bot.name, bot.color, &others);
+            let identity = Identity {
+                name,
+                color,
+                skin: bot.skin,
+            };
+            info!("Adding external bot {}", identity.name);
+            occupied.insert(position);
+            taken.push(identity.clone());
+            let snake = spawn_snake(&mut commands, position, direction, spawns.initial_tail);
+            let player = Player(next_player);
+            next_player += 1;
+            commands.entity(snake).insert(player).insert(identity);
+            commands.entity(snake).insert(AiController {
+                brain: Box::new(bot.brain),
+            });
+        }
+    }
+}
+
+/// Removes the snakes of external bots whose connection has closed.
+fn despawn_disconnected_bots(mut commands: Commands, bots: Query<(Entity, &SnakeHead, &AiController)>) {
+    for (entity, head, ai) in bots.iter() {
+        if ai.brain.is_disconnected() {
+            for tail in &head.tail {
+                commands.entity(*tail).despawn();
+            }
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+    use crate::mode::components::{GameMode, ModeSettings};
+    use crate::snake::components::Dying;
+    use crate::snake::TICK_SECONDS;
+    use crate::{ai, food, mode, snake};
+
+    #[test]
+    fn external_bots_respawn_with_their_connection() {
+        let mut app = App::new();
+        app.insert_resource(Time::default())
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(GameRng::from_seed(0))
+            .insert_resource(ModeSettings {
+                mode: GameMode::FreeForAll,
+                respawn_seconds: 0.1,
+                ..default()
+            })
+            .add_loopless_state(GameState::Running)
+            .add_plugin(snake::SnakePlugin)
+            .add_plugin(food::FoodPlugin)
+            .add_plugin(ai::AiPlugin)
+            .add_plugin(mode::ModePlugin);
+        let mut clock = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(clock);
+
+        let (brain, _link) = link("my-bot".to_string(), Duration::from_millis(1), false);
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        let snake = spawn_snake(&mut commands, Position { x: 5, y: 5 }, Direction::Right, 2);
+        commands.entity(snake).insert(Player(1)).insert(AiController { brain: Box::new(brain) }).insert(Dying);
+        queue.apply(&mut app.world);
+
+        for _ in 0..10 {
+            clock += Duration::from_secs_f32(TICK_SECONDS);
+            app.world.resource_mut::<Time>().update_with_instant(clock);
+            app.update();
+        }
+        assert!(app.world.get_entity(snake).is_none());
+        let bots: Vec<(Player, String)> = app
+            .world
+            .query::<(&Player, &AiController)>()
+            .iter(&app.world)
+            .map(|(player, ai)| (*player, ai.brain.name().to_string()))
+            .collect();
+        assert_eq!(bots, vec![(Player(1), "my-bot".to_string())]);
+    }
+
+    #[test]
+    fn bots_in_a_live_game_are_not_waited_for() {
+        let (mut brain, link) = link("my-bot".to_string(), Duration::from_secs(10), false);
+        let board = Board {
+            snakes: vec![SnakeView {
+                head: Position { x: 5, y: 5 },
+                direction: Direction::Right,
+                body: vec![],
+            }],
+            food: vec![],
+        };
+        let asked = Instant::now();
+        brain.prepare(&board, 0, asked);
+        assert_eq!(brain.decide(&board, 0), Direction::Right);
+        assert!(asked.elapsed() < Duration::from_secs(1));
+
+        // The reply to the state before is too late
+        link.replies
+            .send((
+                Reply {
+                    tick: 1,
+                    direction: Direction::Up,
+                },
+                Instant::now(),
+            ))
+            .unwrap();
+        brain.prepare(&board, 0, Instant::now());
+        link.replies
+            .send((
+                Reply {
+                    tick: 2,
+                    direction: Direction::Down,
+                },
+                Instant::now(),
+            ))
+            .unwrap();
+        assert_eq!(brain.decide(&board, 0), Direction::Down);
+    }
+}
@@ -1 +1,2 @@
+pub mod bot_api;
 pub mod server;
\ No newline at end of file
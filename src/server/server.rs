@@ -1,4 +1,43 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use iyes_loopless::prelude::*;
+use quinn::{Connecting, Connection, Endpoint};
+use tokio::sync::watch;
+
+use crate::ai::MAX_BOTS;
+use crate::common::components::Position;
+use crate::common::conditioner::LinkConditions;
+use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
+use crate::common::discovery::{self, DiscoveryConfig, ServerInfo, PROTOCOL_VERSION};
+use crate::common::protocol::{
+    self, DisconnectReason, Hello, NetMessage, Snapshot, StateUpdate, Welcome, MAX_HANDSHAKE_BYTES, RECONNECT_GRACE,
+};
 use crate::common::quinn_helpers::make_server_endpoint;
+use crate::common::snapshot::{sort_food, SnapshotHistory};
+use crate::common::transport::{Link, Received, StreamLimits};
+use crate::config::UserConfig;
+use crate::food::components::Food;
+use crate::mode::components::{MatchResults, MatchState, ModeSettings, Player, ServerClock};
+use crate::net::components::{
+    CloseConnections, NetMessageReceived, Peer, PlayerConnected, PlayerDisconnected, Recipient, SendNetMessage,
+    SendState, StateReceived,
+};
+use crate::net::NetRuntime;
+use crate::player::components::{Identity, LocalIdentity};
+use crate::player::{color_hex, parse_color, settle_identity};
+use crate::snake::components::{Dying, SnakeDied, SnakeHead, SnakeState};
+use crate::snake::SNAKE_TICK;
+use crate::state::GameState;
+
+/// Port the server accepts QUIC connections on.
+pub const SERVER_PORT: u16 = 5000;
 
 // pub fn server_main() {
 //     let code = {
@@ -12,17 +51,683 @@ use crate::common::quinn_helpers::make_server_endpoint;
 //     ::std::process::exit(code);
 // }
 
-pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let server_addr = "127.0.0.1:5000".parse().unwrap();
-    let (endpoint, server_cert) = make_server_endpoint(server_addr)?;
-    
-    // Single connection
-    let incoming_conn = endpoint.accept().await.unwrap();
-    let conn = incoming_conn.await.unwrap();
-    println!(
-        "[server] connection accepted: addr={}",
-        conn.remote_address()
+/// Something that happened to one of the clients of the server, for the host to hear about.
+#[derive(Debug)]
+pub enum ServerEvent {
+    Joined {
+        slot: usize,
+        name: String,
+    },
+    /// A client came back to the slot it lost the connection to
+    Rejoined {
+        slot: usize,
+        name: String,
+    },
+    /// A client's connection dropped, and its slot is kept for it for [`RECONNECT_GRACE`]
+    Lost {
+        slot: usize,
+        name: String,
+        reason: DisconnectReason,
+    },
+    /// A client is gone for good, freeing its slot
+    Left {
+        slot: usize,
+        name: String,
+        reason: DisconnectReason,
+    },
+    /// A client that wasn't let in
+    Rejected {
+        addr: SocketAddr,
+        reason: DisconnectReason,
+    },
+    /// A message from a client
+    Received {
+        slot: usize,
+        message: NetMessage,
+    },
+    /// The latest state from a client
+    State {
+        slot: usize,
+        state: StateUpdate,
+    },
+}
+
+impl fmt::Display for ServerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Joined { slot, name } => write!(f, "{} joined as player {}", name, slot),
+            Self::Rejoined { slot, name } => write!(f, "{} rejoined as player {}", name, slot),
+            Self::Lost { name, reason, .. } => write!(f, "{} lost the connection ({}), waiting for them", name, reason),
+            Self::Left { name, reason, .. } => write!(f, "{} left: {}", name, reason),
+            Self::Rejected { addr, reason } => write!(f, "turned away {}: {}", addr, reason),
+            Self::Received { slot, message } => write!(f, "player {} sent {:?}", slot, message),
+            Self::State { slot, state } => write!(f, "player {} is at {:?}", slot, state),
+        }
+    }
+}
+
+/// A client's place on the server, kept across reconnects by its session token.
+struct Session {
+    slot: usize,
+    name: String,
+    color: Color,
+    /// `None` while the client is reconnecting
+    link: Option<Link>,
+    /// Bumped whenever the connection changes, so a stale grace period can tell it's been overtaken
+    generation: u64,
+}
+
+struct Sessions {
+    by_token: HashMap<u64, Session>,
+    /// Who's playing in slot 0
+    host: Identity,
+}
+
+impl Sessions {
+    fn new(host: Identity) -> Self {
+        Self {
+            by_token: HashMap::new(),
+            host,
+        }
+    }
+
+    /// The name and color `hello` asks for, changed to ones nobody else has where another player has them.
+    /// The session being resumed, if any, keeps its own.
+    fn settle_identity(&self, hello: &Hello) -> (String, Color) {
+        let others: Vec<(&str, &Color)> = self
+            .by_token
+            .iter()
+            .filter(|(token, _)| hello.session != Some(**token))
+            .map(|(_, session)| (session.name.as_str(), &session.color))
+            .chain([(self.host.name.as_str(), &self.host.color)])
+            .collect();
+        settle_identity(&hello.name, parse_color(&hello.color).ok(), &others)
+    }
+
+    /// Lowest slot nobody holds, if there's one below `max_players`. Slot 0 is the host's.
+    fn free_slot(&self, max_players: usize) -> Option<usize> {
+        (1..max_players).find(|slot| self.by_token.values().all(|session| session.slot != *slot))
+    }
+}
+
+/// The running server, for the game to reach its clients, kick them and shut it down with.
+pub struct ServerHandle {
+    endpoint: Endpoint,
+    sessions: Arc<Mutex<Sessions>>,
+    events: Sender<ServerEvent>,
+}
+
+impl ServerHandle {
+    /// Disconnects the client in `slot`, who can't resume the session.
+    pub fn kick(&self, slot: usize) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let token = sessions.by_token.iter().find(|(_, session)| session.slot == slot).map(|(token, _)| *token);
+        if let Some(session) = token.and_then(|token| sessions.by_token.remove(&token)) {
+            if let Some(link) = &session.link {
+                DisconnectReason::Kicked.close(link.connection());
+            }
+            let _ = self.events.send(ServerEvent::Left {
+                slot,
+                name: session.name,
+                reason: DisconnectReason::Kicked,
+            });
+        }
+    }
+
+    /// Link to the client in `slot`, while it's connected.
+    pub fn link(&self, slot: usize) -> Option<Link> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.by_token.values().find(|session| session.slot == slot)?.link.clone()
+    }
+
+    /// Links to every connected client.
+    pub fn links(&self) -> Vec<Link> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.by_token.values().filter_map(|session| session.link.clone()).collect()
+    }
+
+    /// Closes every connection, telling the clients the server is going away.
+    pub fn shutdown(&self) {
+        let (code, reason) = DisconnectReason::ServerShutdown.close_code();
+        self.endpoint.close(code, reason.as_bytes());
+        self.sessions.lock().unwrap().by_token.clear();
+    }
+}
+
+/// Starts accepting clients on [`SERVER_PORT`] on the current Tokio runtime, announcing `info` on the LAN and
+/// answering discovery queries with it meanwhile, and reporting what the clients do to `events`.
+pub fn start(
+    info: watch::Receiver<ServerInfo>,
+    host: Identity,
+    limits: StreamLimits,
+    conditions: LinkConditions,
+    events: Sender<ServerEvent>,
+) -> Result<ServerHandle, Box<dyn Error>> {
+    let server_addr: SocketAddr = (Ipv4Addr::UNSPECIFIED, SERVER_PORT).into();
+    let (endpoint, _server_cert) = make_server_endpoint(server_addr, limits, conditions)?;
+    let sessions = Arc::new(Mutex::new(Sessions::new(host)));
+
+    let announced = info.clone();
+    tokio::spawn(async move {
+        if let Err(e) = discovery::serve(announced, DiscoveryConfig::default()).await {
+            println!("[discovery] stopped announcing: {}", e);
+        }
+    });
+
+    let accepting = endpoint.clone();
+    let handle = ServerHandle {
+        endpoint,
+        sessions: sessions.clone(),
+        events: events.clone(),
+    };
+    tokio::spawn(async move {
+        while let Some(incoming_conn) = accepting.accept().await {
+            let max_players = info.borrow().max_players;
+            tokio::spawn(serve_client(
+                incoming_conn,
+                sessions.clone(),
+                max_players,
+                events.clone(),
+            ));
+        }
+    });
+
+    Ok(handle)
+}
+
+/// Lets a client in, or tells it why not, and looks after its slot once its connection closes.
+async fn serve_client(
+    incoming_conn: Connecting,
+    sessions: Arc<Mutex<Sessions>>,
+    max_players: usize,
+    events: Sender<ServerEvent>,
+) {
+    let conn = match incoming_conn.await {
+        Ok(conn) => conn,
+        Err(e) => {
+            println!("[server] connection failed: {}", e);
+            return;
+        }
+    };
+    let addr = conn.remote_address();
+
+    // Pings close the connection without saying hello
+    let (mut send, recv) = match conn.accept_bi().await {
+        Ok(streams) => streams,
+        Err(_) => return,
+    };
+    let hello: Hello = match recv.read_to_end(MAX_HANDSHAKE_BYTES).await {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(hello) => hello,
+            Err(_) => return DisconnectReason::Left.close(&conn),
+        },
+        Err(_) => return,
+    };
+
+    let admitted = if hello.version != PROTOCOL_VERSION {
+        Err(DisconnectReason::VersionMismatch(PROTOCOL_VERSION))
+    } else {
+        Ok(admit(
+            &mut sessions.lock().unwrap(),
+            &hello,
+            &conn,
+            max_players,
+            &events,
+        ))
+    };
+    let (token, welcome) = match admitted {
+        Ok(Some(admitted)) => admitted,
+        Ok(None) => return reject(&conn, DisconnectReason::ServerFull, &events),
+        Err(reason) => return reject(&conn, reason, &events),
+    };
+    let welcomed = match serde_json::to_vec(&welcome) {
+        Ok(bytes) => send.write_all(&bytes).await.is_ok() && send.finish().await.is_ok(),
+        Err(_) => false,
+    };
+    let (slot, name) = (welcome.slot, welcome.name);
+    let _ = events.send(if welcome.resumed {
+        ServerEvent::Rejoined { slot, name }
+    } else {
+        ServerEvent::Joined { slot, name }
+    });
+    println!("[server] client joined: addr={} slot={}", addr, slot);
+
+    // Hold on to the connection until the client is done with it, its link handing on what it says meanwhile
+    let reason = if welcomed {
+        DisconnectReason::from_error(&conn.closed().await)
+    } else {
+        DisconnectReason::ConnectionLost("couldn't welcome the client".to_string())
+    };
+    println!("[server] connection closed: addr={} reason={}", addr, reason);
+
+    let generation = {
+        let mut sessions = sessions.lock().unwrap();
+        // A kicked client's session is gone already, and a resumed one has moved on to a newer connection
+        let session = match sessions.by_token.get_mut(&token) {
+            Some(session)
+                if session.link.as_ref().map(|link| link.connection().stable_id()) == Some(conn.stable_id()) =>
+            {
+                session
+            }
+            _ => return,
+        };
+        let (slot, name) = (session.slot, session.name.clone());
+        if !reason.can_resume() {
+            sessions.by_token.remove(&token);
+            let _ = events.send(ServerEvent::Left { slot, name, reason });
+            return;
+        }
+        session.link = None;
+        session.generation += 1;
+        let _ = events.send(ServerEvent::Lost { slot, name, reason });
+        session.generation
+    };
+
+    tokio::time::sleep(RECONNECT_GRACE).await;
+    let mut sessions = sessions.lock().unwrap();
+    let expired = sessions.by_token.get(&token).is_some_and(|session| session.generation == generation);
+    if let Some(session) = expired.then(|| sessions.by_token.remove(&token)).flatten() {
+        let _ = events.send(ServerEvent::Left {
+            slot: session.slot,
+            name: session.name,
+            reason: DisconnectReason::TimedOut,
+        });
+    }
+}
+
+/// Gives the client the slot of the session it's resuming, or a free one, returning its session token and the
+/// welcome to send it. `None` if the server is full.
+fn admit(
+    sessions: &mut Sessions,
+    hello: &Hello,
+    conn: &Connection,
+    max_players: usize,
+    events: &Sender<ServerEvent>,
+) -> Option<(u64, Welcome)> {
+    let (name, color) = sessions.settle_identity(hello);
+    let resumed = hello.session.and_then(|token| Some((token, sessions.by_token.get_mut(&token)?)));
+    if let Some((token, session)) = resumed {
+        // The old connection may not have noticed it's gone yet
+        if let Some(old) = session.link.replace(open_link(conn, session.slot, events)) {
+            DisconnectReason::Left.close(old.connection());
+        }
+        session.name = name.clone();
+        session.color = color;
+        session.generation += 1;
+        let welcome = Welcome {
+            slot: session.slot,
+            session: token,
+            resumed: true,
+            name,
+            color: color_hex(color),
+        };
+        return Some((token, welcome));
+    }
+
+    let slot = sessions.free_slot(max_players)?;
+    let token = rand::random();
+    sessions.by_token.insert(
+        token,
+        Session {
+            slot,
+            name: name.clone(),
+            color,
+            link: Some(open_link(conn, slot, events)),
+            generation: 0,
+        },
     );
-    
-    Ok(())
-}
\ No newline at end of file
+    Some((
+        token,
+        Welcome {
+            slot,
+            session: token,
+            resumed: false,
+            name,
+            color: color_hex(color),
+        },
+    ))
+}
+
+/// Starts talking to the client in `slot`, handing what it sends to `events`.
+fn open_link(conn: &Connection, slot: usize, events: &Sender<ServerEvent>) -> Link {
+    let events = events.clone();
+    Link::open(conn.clone(), move |received| {
+        let _ = events.send(match received {
+            Received::Message(message) => ServerEvent::Received { slot, message },
+            Received::State(state) => ServerEvent::State { slot, state },
+        });
+    })
+}
+
+fn reject(conn: &Connection, reason: DisconnectReason, events: &Sender<ServerEvent>) {
+    reason.close(conn);
+    let _ = events.send(ServerEvent::Rejected {
+        addr: conn.remote_address(),
+        reason,
+    });
+}
+
+/// Latest [`ServerInfo`], for [`start`] to announce.
+pub struct ServerInfoSender(pub watch::Sender<ServerInfo>);
+
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replication>()
+            .add_startup_system(start_server)
+            .add_system(update_server_info)
+            .add_system(receive_server_events)
+            .add_system(announce_players.after(receive_server_events))
+            .add_system(receive_acks.after(receive_server_events))
+            .add_system(announce_phase.after(receive_server_events))
+            // The snake tick's sub-stages are added by the snake plugin, before this one
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                0,
+                send_snapshot.run_in_state(GameState::Running).before(SnakeState::Movement),
+            )
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                2,
+                send_deaths.run_in_state(GameState::Running).after(SnakeState::Removal),
+            )
+            .add_enter_system(GameState::Results, send_match_result)
+            .add_system_to_stage(CoreStage::Last, shutdown_server.label(CloseConnections));
+    }
+}
+
+/// Snapshots sent to the clients, and the newest one each client has.
+#[derive(Default)]
+struct Replication {
+    history: SnapshotHistory,
+    /// Tick of the newest snapshot each client acknowledged, by slot
+    acks: HashMap<usize, u64>,
+}
+
+/// A client playing on this server.
+pub struct RemotePlayer {
+    pub slot: usize,
+    pub name: String,
+    /// Whether the client is connected, rather than its slot being kept for it to reconnect
+    pub connected: bool,
+}
+
+/// What's going on with the clients of this server, as far as the host needs to know.
+pub struct ServerStatus {
+    /// Clients holding a slot, by slot
+    pub players: Vec<RemotePlayer>,
+    /// Latest news of the clients, for the status line
+    pub last_event: Option<String>,
+    events: Receiver<ServerEvent>,
+}
+
+impl ServerStatus {
+    fn new(events: Receiver<ServerEvent>) -> Self {
+        Self {
+            players: vec![],
+            last_event: None,
+            events,
+        }
+    }
+}
+
+/// Starts hosting the game, which carries on without a server if it can't, like when another game on this
+/// machine has the port.
+fn start_server(
+    mut commands: Commands,
+    runtime: Res<NetRuntime>,
+    limits: Res<StreamLimits>,
+    conditions: Res<LinkConditions>,
+    config: Res<UserConfig>,
+    mode: Res<ModeSettings>,
+    local: Res<LocalIdentity>,
+) {
+    let (info, info_rx) = watch::channel(server_info(&config, &mode, 0));
+    let (events, events_rx) = unbounded();
+    let _runtime = runtime.0.enter();
+    match start(info_rx, local.0.clone(), *limits, *conditions, events) {
+        Ok(server) => commands.insert_resource(server),
+        Err(e) => warn!("Not hosting a server: {}", e),
+    }
+    commands.insert_resource(ServerInfoSender(info));
+    commands.insert_resource(ServerStatus::new(events_rx));
+}
+
+/// Keeps track of the clients, and hands what they do to the game as events.
+fn receive_server_events(
+    mut status: ResMut<ServerStatus>,
+    mut connected: EventWriter<PlayerConnected>,
+    mut disconnected: EventWriter<PlayerDisconnected>,
+    mut received: EventWriter<NetMessageReceived>,
+    mut states: EventWriter<StateReceived>,
+) {
+    let events: Vec<ServerEvent> = status.events.try_iter().collect();
+    for event in events {
+        // Only news of the clients coming and going makes the status line
+        if !matches!(event, ServerEvent::Received { .. } | ServerEvent::State { .. }) {
+            info!("{}", event);
+            status.last_event = Some(event.to_string());
+        }
+        let players = &mut status.players;
+        let resumed = matches!(event, ServerEvent::Rejoined { .. });
+        match event {
+            ServerEvent::Joined { slot, name } | ServerEvent::Rejoined { slot, name } => {
+                players.retain(|player| player.slot != slot);
+                players.push(RemotePlayer {
+                    slot,
+                    name: name.clone(),
+                    connected: true,
+                });
+                players.sort_by_key(|player| player.slot);
+                connected.send(PlayerConnected { slot, name, resumed });
+            }
+            ServerEvent::Lost { slot, name, reason } => {
+                if let Some(player) = players.iter_mut().find(|player| player.slot == slot) {
+                    player.connected = false;
+                }
+                disconnected.send(PlayerDisconnected {
+                    slot,
+                    name,
+                    reason,
+                    resumable: true,
+                });
+            }
+            ServerEvent::Left { slot, name, reason } => {
+                players.retain(|player| player.slot != slot);
+                disconnected.send(PlayerDisconnected {
+                    slot,
+                    name,
+                    reason,
+                    resumable: false,
+                });
+            }
+            ServerEvent::Rejected { .. } => {}
+            ServerEvent::Received { slot, message } => received.send(NetMessageReceived {
+                from: Peer::Client(slot),
+                message,
+            }),
+            ServerEvent::State { slot, state } => states.send(StateReceived {
+                from: Peer::Client(slot),
+                state,
+            }),
+        }
+    }
+}
+
+/// Lets every client know who comes and goes.
+fn announce_players(
+    mut connected: EventReader<PlayerConnected>,
+    mut disconnected: EventReader<PlayerDisconnected>,
+    mut outgoing: EventWriter<SendNetMessage>,
+) {
+    let joined = connected.iter().map(|player| NetMessage::PlayerJoined {
+        slot: player.slot,
+        name: player.name.clone(),
+    });
+    let left = disconnected.iter().map(|player| NetMessage::PlayerLeft {
+        slot: player.slot,
+        name: player.name.clone(),
+        reason: player.reason.to_string(),
+    });
+    for message in joined.chain(left).collect::<Vec<_>>() {
+        outgoing.send(SendNetMessage {
+            to: Recipient::Clients,
+            message,
+        });
+    }
+}
+
+/// Sends the clients the state of the board every tick, as of before the snakes move, each as changes to the
+/// newest snapshot it acknowledged.
+fn send_snapshot(
+    status: Res<ServerStatus>,
+    mut replication: ResMut<Replication>,
+    snakes: Query<(&Player, &Position, &SnakeHead), Without<Dying>>,
+    positions: Query<&Position>,
+    food: Query<&Position, With<Food>>,
+    mut outgoing: EventWriter<SendState>,
+) {
+    if status.players.iter().all(|player| !player.connected) {
+        return;
+    }
+    let mut snakes: Vec<protocol::SnakeState> = snakes
+        .iter()
+        .map(|(player, position, head)| protocol::SnakeState {
+            player: player.0,
+            head: *position,
+            direction: head.direction,
+            body: positions.iter_many(&head.tail).copied().collect(),
+        })
+        .collect();
+    snakes.sort_by_key(|snake| snake.player);
+    let mut food: Vec<Position> = food.iter().copied().collect();
+    sort_food(&mut food);
+    let snapshot = Snapshot {
+        tick: replication.history.latest().map_or(1, |latest| latest.tick + 1),
+        snakes,
+        food,
+    };
+
+    for player in status.players.iter().filter(|player| player.connected) {
+        let base = replication.acks.get(&player.slot).and_then(|tick| replication.history.get(*tick));
+        let state = match base {
+            Some(base) => StateUpdate::Delta(snapshot.delta_from(base)),
+            None => StateUpdate::Snapshot(snapshot.clone()),
+        };
+        outgoing.send(SendState {
+            to: Recipient::Client(player.slot),
+            state,
+        });
+    }
+    replication.history.push(snapshot);
+}
+
+/// Keeps track of the snapshots the clients have, starting over with each connection.
+fn receive_acks(
+    mut connected: EventReader<PlayerConnected>,
+    mut states: EventReader<StateReceived>,
+    mut replication: ResMut<Replication>,
+) {
+    for player in connected.iter() {
+        replication.acks.remove(&player.slot);
+    }
+    for received in states.iter() {
+        if let (Peer::Client(slot), StateUpdate::Ack { tick }) = (received.from, &received.state) {
+            let ack = replication.acks.entry(slot).or_default();
+            *ack = (*ack).max(*tick);
+        }
+    }
+}
+
+/// Tells the clients about each snake that died, and the food it left, as it's removed.
+fn send_deaths(
+    mut died: EventReader<SnakeDied>,
+    snakes: Query<(&Player, Option<&Identity>)>,
+    mut outgoing: EventWriter<SendNetMessage>,
+) {
+    for death in died.iter() {
+        // Only removed at the end of the stage
+        if let Ok((player, identity)) = snakes.get(death.snake) {
+            outgoing.send(SendNetMessage {
+                to: Recipient::Clients,
+                message: NetMessage::Died {
+                    player: player.0,
+                    name: identity.map_or_else(|| player.to_string(), |identity| identity.name.clone()),
+                    food: death.food.clone(),
+                },
+            });
+        }
+    }
+}
+
+fn send_match_result(results: Option<Res<MatchResults>>, mut outgoing: EventWriter<SendNetMessage>) {
+    if let Some(results) = results {
+        outgoing.send(SendNetMessage {
+            to: Recipient::Clients,
+            message: NetMessage::MatchResult {
+                winner: results.winner.clone(),
+                standings: results.standings.iter().map(|standing| (standing.name.clone(), standing.score)).collect(),
+            },
+        });
+    }
+}
+
+/// Tells the clients when each countdown and intermission ends, and the time on this game's clock, so theirs
+/// end at the same moment. Clients that just joined are told about the current one.
+fn announce_phase(
+    time: Res<Time>,
+    clock: Res<ServerClock>,
+    state: Res<MatchState>,
+    mut connected: EventReader<PlayerConnected>,
+    mut announced: Local<f64>,
+    mut outgoing: EventWriter<SendNetMessage>,
+) {
+    let joined = connected.iter().count() > 0;
+    if state.phase_ends_at == *announced && !joined {
+        return;
+    }
+    *announced = state.phase_ends_at;
+    outgoing.send(SendNetMessage {
+        to: Recipient::Clients,
+        message: NetMessage::Phase {
+            server_time: clock.now(&time),
+            ends_at: state.phase_ends_at,
+        },
+    });
+}
+
+/// Says goodbye to the clients when the game closes.
+fn shutdown_server(mut exits: EventReader<AppExit>, server: Option<Res<ServerHandle>>) {
+    if let (Some(_), Some(server)) = (exits.iter().last(), server) {
+        server.shutdown();
+    }
+}
+
+/// Keeps what the server announces in step with the game being played.
+fn update_server_info(
+    sender: Res<ServerInfoSender>,
+    config: Res<UserConfig>,
+    mode: Res<ModeSettings>,
+    heads: Query<(), With<SnakeHead>>,
+) {
+    let info = server_info(&config, &mode, heads.iter().count());
+    sender.0.send_if_modified(|current| {
+        let changed = *current != info;
+        *current = info;
+        changed
+    });
+}
+
+pub fn server_info(config: &UserConfig, mode: &ModeSettings, players: usize) -> ServerInfo {
+    ServerInfo {
+        name: format!("{}'s game", config.name),
+        port: SERVER_PORT,
+        version: PROTOCOL_VERSION,
+        map: format!("{}x{}", ARENA_WIDTH, ARENA_HEIGHT),
+        mode: mode.mode.to_string(),
+        players,
+        // The local player and a full set of bots
+        max_players: MAX_BOTS + 1,
+    }
+}
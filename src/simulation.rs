@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::ai::board::SnakeView;
+use crate::ai::brain::SnakeBrain;
+use crate::ai::components::AiController;
+use crate::common::components::{Direction, Position};
+use crate::common::rng::GameRng;
+use crate::food::components::{Food, FoodKind};
+use crate::snake::components::{SnakeHead, SpawnSettings};
+use crate::snake::{spawn_snake, TICK_SECONDS};
+use crate::state::GameState;
+use crate::{ai, food, snake};
+
+/// Headless game world running the same snake, food and AI plugins as the windowed game.
+///
+/// Time is advanced manually by exactly one [`SNAKE_TICK`](crate::snake::SNAKE_TICK) per [`Simulation::step`],
+/// so a simulation created with the same seed and snakes always plays out the same way.
+pub struct Simulation {
+    app: App,
+    clock: Instant,
+    pub tick: u64,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        let mut app = App::new();
+        app.insert_resource(Time::default())
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(GameRng::from_seed(seed))
+            .add_loopless_state(GameState::Running)
+            .add_plugin(snake::SnakePlugin)
+            .add_plugin(food::FoodPlugin)
+            .add_plugin(ai::AiPlugin);
+
+        let clock = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(clock);
+        Self { app, clock, tick: 0 }
+    }
+
+    /// Adds a snake steered by `brain`, returning its head entity.
+    pub fn spawn_snake(&mut self, position: Position, direction: Direction, brain: Box<dyn SnakeBrain>) -> Entity {
+        let snake = self.spawn_player(position, direction);
+        self.app.world.entity_mut(snake).insert(AiController { brain });
+        snake
+    }
+
+    /// Adds a snake steered with [`Simulation::steer`], returning its head entity.
+    pub fn spawn_player(&mut self, position: Position, direction: Direction) -> Entity {
+        let tail = self.app.world.resource::<SpawnSettings>().initial_tail;
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &self.app.world);
+        let snake = spawn_snake(&mut commands, position, direction, tail);
+        queue.apply(&mut self.app.world);
+        snake
+    }
+
+    /// Drops food of `kind` at `position` that stays until it's eaten, returning its entity.
+    pub fn spawn_food(&mut self, position: Position, kind: FoodKind) -> Entity {
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &self.app.world);
+        let food = food::spawn_food_at(&mut commands, position, kind, None);
+        queue.apply(&mut self.app.world);
+        food
+    }
+
+    /// Turns `snake` towards `direction` on its next move, ignoring reversals like keyboard input does.
+    pub fn steer(&mut self, snake: Entity, direction: Direction) {
+        if let Some(mut head) = self.app.world.get_mut::<SnakeHead>(snake) {
+            if direction != head.direction.opposite() {
+                head.input_direction = direction;
+            }
+        }
+    }
+
+    /// Advances the game by one tick.
+    pub fn step(&mut self) {
+        self.clock += Duration::from_secs_f32(TICK_SECONDS);
+        let clock = self.clock;
+        self.app.world.resource_mut::<Time>().update_with_instant(clock);
+        self.app.update();
+        self.tick += 1;
+    }
+
+    /// Length of the snake including its head, or `None` once it is gone.
+    pub fn snake_length(&self, snake: Entity) -> Option<usize> {
+        self.app.world.get::<SnakeHead>(snake).map(|head| head.tail.len() + 1)
+    }
+
+    /// Current state of `snake`, or `None` once it is gone.
+    pub fn snake_view(&self, snake: Entity) -> Option<SnakeView> {
+        let world = &self.app.world;
+        let head = world.get::<SnakeHead>(snake)?;
+        Some(SnakeView {
+            head: *world.get::<Position>(snake)?,
+            direction: head.direction,
+            body: head.tail.iter().filter_map(|tail| world.get::<Position>(*tail).copied()).collect(),
+        })
+    }
+
+    pub fn food(&mut self) -> Vec<Position> {
+        self.app.world.query_filtered::<&Position, With<Food>>().iter(&self.app.world).copied().collect()
+    }
+}
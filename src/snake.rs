@@ -1,30 +1,144 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
 use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
 use iyes_loopless::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
 
+use crate::ai::components::AiController;
 use crate::common::components::{Direction, Position, Size};
-use crate::snake::components::{SnakeHead, SnakeState, Tail};
+use crate::common::constants::{ARENA_HEIGHT, ARENA_WIDTH};
+use crate::food::components::{Food, FoodKind, FoodSettings};
+use crate::food::spawn_food_at;
+use crate::mode::components::Team;
+use crate::snake::components::{
+    CollisionSettings, Dying, HeadOnRule, Invulnerable, KeyBindings, LastCell, PreviousPosition, SnakeDied, SnakeHead,
+    SnakeState, SpawnSettings, SpeedSettings, Tail, CELL_PROGRESS,
+};
 use crate::state::GameState;
 
 pub mod components;
 
 pub struct SnakePlugin;
 
+// Must be added before the plugins that add systems to the `SNAKE_TICK` timestep
 impl Plugin for SnakePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(snake_movement.run_in_state(GameState::Running).label(SnakeState::Movement))
-            .add_system(snake_movement_input.run_in_state(GameState::Running).after(SnakeState::Movement));
+        app.init_resource::<SpeedSettings>()
+            .init_resource::<CollisionSettings>()
+            .init_resource::<SpawnSettings>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<FoodSettings>()
+            .add_fixed_timestep(Duration::from_secs_f32(TICK_SECONDS), SNAKE_TICK)
+            // Sub-stage 1 is for collisions, once the commands issued while moving have been applied
+            .add_fixed_timestep_child_stage(SNAKE_TICK)
+            // Sub-stage 2 feeds the snakes that survived the tick and removes the ones that died
+            .add_fixed_timestep_child_stage(SNAKE_TICK)
+            // Sub-stage 3 sees the board as the tick left it
+            .add_fixed_timestep_child_stage(SNAKE_TICK)
+            .add_fixed_timestep_system(SNAKE_TICK, 1, detect_collisions.run_in_state(GameState::Running))
+            .add_fixed_timestep_system(SNAKE_TICK, 1, wear_off_invulnerability.run_in_state(GameState::Running))
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                2,
+                kill_snakes.run_in_state(GameState::Running).label(SnakeState::Removal),
+            )
+            .add_event::<SnakeDied>()
+            .add_fixed_timestep_system(
+                SNAKE_TICK,
+                0,
+                snake_movement.run_in_state(GameState::Running).label(SnakeState::Movement),
+            )
+            .add_system(snake_movement_input.run_in_state(GameState::Running))
+            .add_system(blink_invulnerable.run_in_state(GameState::Running));
     }
 }
 
+/// Fixed timestep that snakes move and eat on, so that snakes of any speed move the same way on every machine.
+pub const SNAKE_TICK: &str = "snake_tick";
+/// Seconds per [`SNAKE_TICK`].
+pub const TICK_SECONDS: f32 = 0.05;
+
 const SNAKE_HEAD_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
 const SNAKE_SEGMENT_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
 
-pub fn spawn_snake(mut commands: Commands) {
-    let mut speed_limiter = Timer::from_seconds(0.2, true);
-    // Instant tick the timer so snake starts moving immediately when spawned
-    speed_limiter.tick(Duration::from_secs_f32(0.2));
+/// Starting cells and directions for `count` snakes, from the map's spawn points if it has enough of them.
+///
+/// Otherwise snakes are spaced evenly around a ring in the middle of the arena, the first at the bottom, each
+/// heading anticlockwise along the ring so they start away from the edges and from each other.
+pub fn spawn_points(count: usize, settings: &SpawnSettings) -> Vec<(Position, Direction)> {
+    if count <= settings.points.len() {
+        return settings.points[..count].to_vec();
+    }
+    let (width, height) = (ARENA_WIDTH as f32, ARENA_HEIGHT as f32);
+    let radius = width.min(height) / 2.0 - 3.0;
+    (0..count)
+        .map(|i| {
+            let angle = -FRAC_PI_2 + TAU * i as f32 / count as f32;
+            let position = Position {
+                x: (width / 2.0 + radius * angle.cos()).round() as i32,
+                y: (height / 2.0 + radius * angle.sin()).round() as i32,
+            };
+            // Anticlockwise tangent, snapped to the closest direction
+            let (dx, dy) = (-angle.sin(), angle.cos());
+            let direction = if dx.abs() >= dy.abs() {
+                if dx > 0.0 {
+                    Direction::Right
+                } else {
+                    Direction::Left
+                }
+            } else if dy > 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            };
+            (position, direction)
+        })
+        .collect()
+}
+
+/// Cells a respawning snake keeps from every other snake, where there's room for it.
+pub const SAFE_SPAWN_DISTANCE: i32 = 3;
+
+/// Picks a random free cell at least [`SAFE_SPAWN_DISTANCE`] from every head and tail segment in `occupied`,
+/// or any free cell if the arena is too crowded for that. The snake faces the way with the most room ahead.
+pub fn safe_spawn_point(occupied: &HashSet<Position>, rng: &mut impl Rng) -> Option<(Position, Direction)> {
+    // Distance along one axis, the short way round the arena
+    let wrapped = |d: i32, bound: u32| d.rem_euclid(bound as i32).min((-d).rem_euclid(bound as i32));
+    let cells: Vec<Position> = (0..ARENA_WIDTH as i32)
+        .flat_map(|x| (0..ARENA_HEIGHT as i32).map(move |y| Position { x, y }))
+        .filter(|pos| !occupied.contains(pos))
+        .collect();
+    let safe: Vec<Position> = cells
+        .iter()
+        .filter(|pos| {
+            occupied.iter().all(|other| {
+                wrapped(pos.x - other.x, ARENA_WIDTH).max(wrapped(pos.y - other.y, ARENA_HEIGHT)) >= SAFE_SPAWN_DISTANCE
+            })
+        })
+        .copied()
+        .collect();
+
+    let position = *safe.choose(rng).or_else(|| cells.choose(rng))?;
+    let room = |direction: Direction| {
+        let mut cell = position;
+        (0..ARENA_WIDTH.max(ARENA_HEIGHT))
+            .take_while(|_| {
+                cell = cell.step(direction);
+                !occupied.contains(&cell)
+            })
+            .count()
+    };
+    // Ties go to the first of `Direction::ALL`
+    let direction = Direction::ALL.into_iter().rev().max_by_key(|direction| room(*direction)).unwrap();
+    Some((position, direction))
+}
+
+/// Spawns a snake with `tail` segments under its head, returning the head entity.
+pub fn spawn_snake(commands: &mut Commands, position: Position, direction: Direction, tail: usize) -> Entity {
+    let tail = (0..tail).map(|_| spawn_tail(commands, position)).collect();
     commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
@@ -34,13 +148,21 @@ pub fn spawn_snake(mut commands: Commands) {
             ..default()
         })
         .insert(SnakeHead {
-            input_direction: Direction::Right,
-            direction: Direction::Right,
-            tail: vec![],
-            timer: speed_limiter,
+            input_direction: direction,
+            direction,
+            tail,
+            // Full progress so the snake starts moving immediately when spawned
+            progress: CELL_PROGRESS,
+            boosting: false,
+            boost_cells: 0,
+            speed_ticks: 0,
+            ghost_ticks: 0,
         })
-        .insert(Position { x: 3, y: 3 })
-        .insert(Size::square(0.8));
+        .insert(position)
+        .insert(PreviousPosition(position))
+        .insert(LastCell(position))
+        .insert(Size::square(0.8))
+        .id()
 }
 
 #[inline]
@@ -55,75 +177,394 @@ pub fn spawn_tail(commands: &mut Commands, position: Position) -> Entity {
         })
         .insert(Tail)
         .insert(position)
+        .insert(LastCell(position))
         .insert(Size::square(0.7))
         .id()
 }
 
-fn snake_movement_input(keys: Res<Input<KeyCode>>, mut head_positions: Query<&mut SnakeHead>) {
+fn snake_movement_input(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut head_positions: Query<&mut SnakeHead, Without<AiController>>,
+) {
     for mut head in head_positions.iter_mut() {
-        let dir: Direction = if keys.pressed(KeyCode::Left) {
-            Direction::Left
-        } else if keys.pressed(KeyCode::Down) {
-            Direction::Down
-        } else if keys.pressed(KeyCode::Up) {
-            Direction::Up
-        } else if keys.pressed(KeyCode::Right) {
-            Direction::Right
-        } else {
-            head.input_direction
-        };
+        // Checked in the same order as the arrow keys always were
+        let dir = [Direction::Left, Direction::Down, Direction::Up, Direction::Right]
+            .into_iter()
+            .find(|direction| keys.pressed(bindings.turn(*direction)))
+            .unwrap_or(head.input_direction);
         if dir != head.direction.opposite() {
             head.input_direction = dir;
         }
+        head.boosting = keys.pressed(bindings.boost);
     }
 }
 
 fn snake_movement(
-    time: Res<Time>,
-    mut head_positions: Query<(&mut Position, &mut SnakeHead)>,
+    mut commands: Commands,
+    settings: Res<SpeedSettings>,
+    food_settings: Res<FoodSettings>,
+    foods: Query<(), With<Food>>,
+    mut head_positions: Query<(Entity, &mut Position, &mut PreviousPosition, &mut SnakeHead)>,
     mut positions: Query<&mut Position, Without<SnakeHead>>,
+    mut last_cells: Query<&mut LastCell>,
 ) {
-    for (mut position, mut head) in head_positions.iter_mut() {
-        if head.timer.finished() {
-            // Tail
-            for (i, tail) in head.tail.iter().enumerate().rev() {
-                if i == 0 {
-                    let mut pos = positions.get_mut(*tail).unwrap();
-                    pos.x = position.x;
-                    pos.y = position.y;
-                } else {
-                    let next_x;
-                    let next_y;
-                    // Beat borrow checker
-                    {
-                        let next_pos = positions.get(head.tail[i - 1]).unwrap();
-                        next_x = next_pos.x;
-                        next_y = next_pos.y;
-                    }
-                    let mut pos = positions.get_mut(*tail).unwrap();
-                    pos.x = next_x;
-                    pos.y = next_y;
+    for (snake, mut position, mut previous, mut head) in head_positions.iter_mut() {
+        previous.0 = *position;
+        head.speed_ticks = head.speed_ticks.saturating_sub(1);
+        head.ghost_ticks = head.ghost_ticks.saturating_sub(1);
+        let boosting = settings.is_boosting(&head);
+        head.progress += settings.speed(&head);
+        if head.progress < CELL_PROGRESS {
+            continue;
+        }
+        head.progress %= CELL_PROGRESS;
+
+        for (entity, cell) in [(snake, *position)]
+            .into_iter()
+            .chain(head.tail.iter().filter_map(|tail| positions.get(*tail).ok().map(|position| (*tail, *position))))
+        {
+            if let Ok(mut last_cell) = last_cells.get_mut(entity) {
+                last_cell.0 = cell;
+            }
+        }
+
+        // Tail
+        for (i, tail) in head.tail.iter().enumerate().rev() {
+            if i == 0 {
+                let mut pos = positions.get_mut(*tail).unwrap();
+                pos.x = position.x;
+                pos.y = position.y;
+            } else {
+                let next_x;
+                let next_y;
+                // Beat borrow checker
+                {
+                    let next_pos = positions.get(head.tail[i - 1]).unwrap();
+                    next_x = next_pos.x;
+                    next_y = next_pos.y;
                 }
+                let mut pos = positions.get_mut(*tail).unwrap();
+                pos.x = next_x;
+                pos.y = next_y;
             }
+        }
+
+        // Head
+        head.direction = head.input_direction;
+        *position = position.step(head.direction);
 
-            // Head
-            head.direction = head.input_direction;
-            match &head.input_direction {
-                Direction::Left => {
-                    position.x -= 1;
+        // Boosting spends the tip of the tail, leaving it behind as food
+        if boosting {
+            head.boost_cells += 1;
+            if head.boost_cells >= settings.boost_cells_per_segment {
+                head.boost_cells = 0;
+                let tip = head.tail.pop().unwrap();
+                if food_settings.room(foods.iter().count()) > 0 {
+                    spawn_food_at(&mut commands, *positions.get(tip).unwrap(), FoodKind::Normal, None);
                 }
-                Direction::Up => {
-                    position.y += 1;
+                commands.entity(tip).despawn();
+            }
+        }
+    }
+}
+
+/// Counts down invulnerability, making snakes solid again once it wears off.
+fn wear_off_invulnerability(
+    mut commands: Commands,
+    mut heads: Query<(Entity, &SnakeHead, &mut Invulnerable)>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    for (snake, head, mut invulnerable) in heads.iter_mut() {
+        invulnerable.ticks = invulnerable.ticks.saturating_sub(1);
+        if invulnerable.ticks == 0 {
+            commands.entity(snake).remove::<Invulnerable>();
+            for entity in head.tail.iter().chain([&snake]) {
+                if let Ok(mut visibility) = visibilities.get_mut(*entity) {
+                    visibility.is_visible = true;
+                }
+            }
+        }
+    }
+}
+
+/// Blinks invulnerable snakes a few times a second.
+fn blink_invulnerable(heads: Query<(Entity, &SnakeHead, &Invulnerable)>, mut visibilities: Query<&mut Visibility>) {
+    for (snake, head, invulnerable) in heads.iter() {
+        let visible = invulnerable.ticks / 4 % 2 == 0;
+        for entity in head.tail.iter().chain([&snake]) {
+            if let Ok(mut visibility) = visibilities.get_mut(*entity) {
+                visibility.is_visible = visible;
+            }
+        }
+    }
+}
+
+/// Marks snakes that ran into something this tick as dying.
+///
+/// Every collision is judged from where all snakes ended up after the tick's moves, so the outcome doesn't
+/// depend on which snake happened to move first:
+/// - a head moving onto any tail segment dies, unless the snake is a ghost
+/// - heads in the same cell, or that swapped cells, collide head-on as set by [`HeadOnRule`]
+/// - teammates only collide with each other with friendly fire on
+/// - invulnerable snakes pass through, and are passed through by, everything
+fn detect_collisions(
+    mut commands: Commands,
+    settings: Res<CollisionSettings>,
+    heads: Query<(Entity, &Position, &PreviousPosition, &SnakeHead), Without<Invulnerable>>,
+    positions: Query<&Position, Without<SnakeHead>>,
+    teams: Query<&Team>,
+) {
+    // Teammates pass through each other without friendly fire
+    let collides = |a: Option<&Team>, b: Option<&Team>| settings.friendly_fire || a.is_none() || a != b;
+
+    let mut tails: HashMap<Position, Vec<(Entity, Option<&Team>)>> = HashMap::new();
+    for (snake, _, _, head) in heads.iter() {
+        let team = teams.get(snake).ok();
+        // Segments grown this tick aren't spawned yet, but they sit on the tail tip anyway
+        for position in head.tail.iter().filter_map(|tail| positions.get(*tail).ok()) {
+            tails.entry(*position).or_default().push((snake, team));
+        }
+    }
+
+    let mut dying = HashSet::new();
+    for (snake, position, previous, head) in heads.iter() {
+        let team = teams.get(snake).ok();
+        // Only a head entering a cell can run into something, a fresh segment may still sit under a resting head
+        let moved = position != &previous.0;
+        if moved && head.ghost_ticks == 0 {
+            if let Some(owners) = tails.get(position) {
+                if owners.iter().any(|(owner, owner_team)| {
+                    if *owner == snake {
+                        settings.self_collision
+                    } else {
+                        collides(team, *owner_team)
+                    }
+                }) {
+                    dying.insert(snake);
                 }
-                Direction::Right => {
-                    position.x += 1;
+            }
+        }
+
+        for (other, other_position, other_previous, other_head) in heads.iter() {
+            let other_team = teams.get(other).ok();
+            let same_cell = position == other_position;
+            let swapped = position == &other_previous.0 && other_position == &previous.0 && position != &previous.0;
+            if other == snake || !(same_cell || swapped) || !collides(team, other_team) {
+                continue;
+            }
+            let survives = match settings.head_on {
+                HeadOnRule::BothDie => false,
+                HeadOnRule::LongerWins => head.tail.len() > other_head.tail.len(),
+            };
+            if !survives {
+                dying.insert(snake);
+            }
+        }
+    }
+
+    for snake in dying {
+        commands.entity(snake).insert(Dying);
+    }
+}
+
+/// Removes dying snakes, turning their tail segments into food as far as the food cap allows.
+fn kill_snakes(
+    mut commands: Commands,
+    settings: Res<FoodSettings>,
+    dying: Query<(Entity, &SnakeHead), With<Dying>>,
+    positions: Query<&Position, Without<SnakeHead>>,
+    foods: Query<(), With<Food>>,
+    mut died: EventWriter<SnakeDied>,
+) {
+    let mut room = settings.room(foods.iter().count());
+    for (snake, head) in dying.iter() {
+        let mut food = vec![];
+        for tail in &head.tail {
+            let position = *positions.get(*tail).unwrap();
+            // Segments that haven't unfolded yet share a cell
+            if room > 0 && !food.contains(&position) {
+                food.push(position);
+                room -= 1;
+            }
+            commands.entity(*tail).despawn();
+        }
+        commands.entity(snake).despawn();
+
+        for position in &food {
+            spawn_food_at(&mut commands, *position, FoodKind::Normal, None);
+        }
+        died.send(SnakeDied { snake, food });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A snake as it ended up after the tick's moves.
+    struct Moved {
+        from: Position,
+        to: Position,
+        /// From the neck to the tip
+        tail: Vec<Position>,
+        team: Option<usize>,
+        invulnerable: bool,
+        ghost: bool,
+    }
+
+    fn moved(from: (i32, i32), to: (i32, i32), tail: &[(i32, i32)]) -> Moved {
+        let cell = |(x, y)| Position { x, y };
+        Moved {
+            from: cell(from),
+            to: cell(to),
+            tail: tail.iter().copied().map(cell).collect(),
+            team: None,
+            invulnerable: false,
+            ghost: false,
+        }
+    }
+
+    /// Which of `snakes` die, checking they're the same ones whichever order the snakes were spawned in.
+    fn dying(settings: impl Fn() -> CollisionSettings, snakes: &[Moved]) -> Vec<bool> {
+        let judge = |order: Vec<usize>| {
+            let mut world = World::new();
+            world.insert_resource(settings());
+            let mut heads = vec![Entity::from_raw(0); snakes.len()];
+            for i in order {
+                let snake = &snakes[i];
+                let tail =
+                    snake.tail.iter().map(|position| world.spawn().insert(Tail).insert(*position).id()).collect();
+                let mut head = world.spawn();
+                head.insert(snake.to).insert(PreviousPosition(snake.from)).insert(SnakeHead {
+                    input_direction: Direction::Right,
+                    direction: Direction::Right,
+                    tail,
+                    progress: 0,
+                    boosting: false,
+                    boost_cells: 0,
+                    speed_ticks: 0,
+                    ghost_ticks: if snake.ghost { 10 } else { 0 },
+                });
+                if let Some(team) = snake.team {
+                    head.insert(Team(team));
                 }
-                Direction::Down => {
-                    position.y -= 1;
+                if snake.invulnerable {
+                    head.insert(Invulnerable { ticks: 10 });
                 }
+                heads[i] = head.id();
             }
+            SystemStage::single(detect_collisions).run(&mut world);
+            heads.iter().map(|head| world.get::<Dying>(*head).is_some()).collect::<Vec<bool>>()
+        };
+        let forwards = judge((0..snakes.len()).collect());
+        let backwards = judge((0..snakes.len()).rev().collect());
+        assert_eq!(forwards, backwards, "depends on which snake was spawned first");
+        forwards
+    }
+
+    fn rules(head_on: HeadOnRule, friendly_fire: bool) -> impl Fn() -> CollisionSettings {
+        move || CollisionSettings {
+            head_on,
+            self_collision: true,
+            friendly_fire,
         }
+    }
+
+    #[test]
+    fn heads_meeting_in_a_cell_collide_head_on() {
+        let short = moved((4, 5), (5, 5), &[(4, 5)]);
+        let long = || moved((6, 5), (5, 5), &[(6, 5), (7, 5)]);
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &[short, long()]), [true, true]);
+        let short = moved((4, 5), (5, 5), &[(4, 5)]);
+        assert_eq!(
+            dying(rules(HeadOnRule::LongerWins, true), &[short, long()]),
+            [true, false]
+        );
+        let same = moved((5, 4), (5, 5), &[(5, 4), (5, 3)]);
+        assert_eq!(
+            dying(rules(HeadOnRule::LongerWins, true), &[same, long()]),
+            [true, true]
+        );
+    }
+
+    #[test]
+    fn heads_swapping_cells_collide_head_on() {
+        let snakes = [moved((4, 5), (5, 5), &[(4, 5)]), moved((5, 5), (4, 5), &[(5, 5)])];
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &snakes), [true, true]);
+    }
+
+    #[test]
+    fn heads_running_into_a_tail_die() {
+        // The first snake runs into the second's neck, the second's head has moved on
+        let snakes = [
+            moved((4, 6), (5, 6), &[(4, 6)]),
+            moved((5, 5), (5, 4), &[(5, 5), (5, 6)]),
+        ];
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &snakes), [true, false]);
+        let ouroboros = moved((4, 5), (5, 5), &[(4, 5), (4, 4), (5, 4), (5, 5)]);
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &[ouroboros]), [true]);
+    }
+
+    #[test]
+    fn teammates_pass_through_each_other_without_friendly_fire() {
+        let teammates = || {
+            let mut into_tail = moved((4, 6), (5, 6), &[(4, 6)]);
+            let mut tail = moved((5, 5), (5, 4), &[(5, 5), (5, 6)]);
+            let mut head_on = moved((6, 4), (5, 4), &[(6, 4)]);
+            into_tail.team = Some(0);
+            tail.team = Some(0);
+            head_on.team = Some(0);
+            [into_tail, tail, head_on]
+        };
+        assert_eq!(
+            dying(rules(HeadOnRule::BothDie, false), &teammates()),
+            [false, false, false]
+        );
+        assert_eq!(
+            dying(rules(HeadOnRule::BothDie, true), &teammates()),
+            [true, true, true]
+        );
+        let mut rival = moved((4, 6), (5, 6), &[(4, 6)]);
+        rival.team = Some(1);
+        let [_, tail, _] = teammates();
+        assert_eq!(dying(rules(HeadOnRule::BothDie, false), &[rival, tail]), [true, false]);
+    }
+
+    #[test]
+    fn invulnerable_snakes_pass_through_and_are_passed_through() {
+        let mut invulnerable = moved((4, 6), (5, 6), &[(4, 6)]);
+        invulnerable.invulnerable = true;
+        let tail = moved((5, 5), (5, 4), &[(5, 5), (5, 6)]);
+        assert_eq!(
+            dying(rules(HeadOnRule::BothDie, true), &[invulnerable, tail]),
+            [false, false]
+        );
+
+        let mut invulnerable = moved((4, 5), (5, 5), &[(4, 5), (3, 5)]);
+        invulnerable.invulnerable = true;
+        let into_tail = moved((4, 4), (4, 5), &[(4, 4)]);
+        let head_on = moved((6, 5), (5, 5), &[(6, 5)]);
+        let into_invulnerable = moved((3, 4), (3, 5), &[(3, 4)]);
+        assert_eq!(
+            dying(
+                rules(HeadOnRule::BothDie, true),
+                &[invulnerable, into_tail, head_on, into_invulnerable]
+            ),
+            [false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn ghosts_pass_through_tails_but_not_heads() {
+        let mut ghost = moved((4, 6), (5, 6), &[(4, 6)]);
+        ghost.ghost = true;
+        let tail = moved((5, 5), (5, 4), &[(5, 5), (5, 6)]);
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &[ghost, tail]), [false, false]);
 
-        head.timer.tick(time.delta());
+        let mut ghost = moved((4, 5), (5, 5), &[(4, 5)]);
+        ghost.ghost = true;
+        let head_on = moved((6, 5), (5, 5), &[(6, 5)]);
+        assert_eq!(dying(rules(HeadOnRule::BothDie, true), &[ghost, head_on]), [true, true]);
     }
 }
@@ -1,10 +1,15 @@
-use bevy::prelude::{Component, Entity, SystemLabel, Timer};
+use bevy::prelude::{Component, Entity, KeyCode, SystemLabel};
+use serde::{Deserialize, Serialize};
 
-use crate::common::components::Direction;
+use crate::common::components::{Direction, Position};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
 pub enum SnakeState {
     Movement,
+    /// Feeding the snakes that didn't die this tick
+    Eating,
+    /// Removing dying snakes, which are still there to look at until the end of the stage
+    Removal,
 }
 
 #[derive(Component)]
@@ -12,8 +17,181 @@ pub struct SnakeHead {
     pub input_direction: Direction,
     pub direction: Direction,
     pub tail: Vec<Entity>,
-    pub timer: Timer,
+    /// Progress towards the next cell, moving once it reaches [`CELL_PROGRESS`]
+    pub progress: u32,
+    pub boosting: bool,
+    /// Cells travelled while boosting since the last tail segment was spent
+    pub boost_cells: u32,
+    /// Ticks left of a speed pellet's effect
+    pub speed_ticks: u32,
+    /// Ticks left that the snake can pass through tails
+    pub ghost_ticks: u32,
 }
 
 #[derive(Component)]
 pub struct Tail;
+
+/// Cell a head or tail segment was in before its latest move, which it's drawn sliding from as the snake
+/// makes progress towards its next move.
+#[derive(Component, Clone, Copy)]
+pub struct LastCell(pub Position);
+
+/// Keeps a freshly respawned snake from dying, or killing anyone, until the ticks run out. The snake blinks
+/// meanwhile.
+#[derive(Component)]
+pub struct Invulnerable {
+    pub ticks: u32,
+}
+
+/// Where a snake's head was at the start of the current tick, the same as its position if it didn't move.
+#[derive(Component, Clone, Copy)]
+pub struct PreviousPosition(pub Position);
+
+/// What happens when two heads meet, either in the same cell or by swapping cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadOnRule {
+    BothDie,
+    /// The shorter snake dies, or both when they're the same length
+    LongerWins,
+}
+
+/// Rules for snakes running into each other, set per game mode.
+pub struct CollisionSettings {
+    pub head_on: HeadOnRule,
+    /// Whether snakes die running into their own tail
+    pub self_collision: bool,
+    /// Whether snakes on the same team collide with each other
+    pub friendly_fire: bool,
+}
+
+impl Default for CollisionSettings {
+    fn default() -> Self {
+        Self {
+            head_on: HeadOnRule::BothDie,
+            self_collision: true,
+            friendly_fire: true,
+        }
+    }
+}
+
+/// Marks a snake that died this tick. It's removed, and its tail turned into food, at the end of the tick.
+#[derive(Component)]
+pub struct Dying;
+
+/// Sent once a snake is removed, with the food its tail turned into. This is the single event clients need
+/// to replay a death, rather than one spawn per piece of food.
+pub struct SnakeDied {
+    pub snake: Entity,
+    pub food: Vec<Position>,
+}
+
+/// Where snakes start and how long, set per game mode.
+#[derive(Default)]
+pub struct SpawnSettings {
+    /// Spawn points defined by the map. Used when there are enough of them for every snake, otherwise snakes
+    /// are spread around the arena
+    pub points: Vec<(Position, Direction)>,
+    /// Tail segments snakes start with, unfolding from the head as it moves off
+    pub initial_tail: usize,
+}
+
+/// Movement progress needed to advance one cell. Speeds are measured in progress per tick, with integers so
+/// that every machine simulating a game moves snakes at exactly the same ticks.
+pub const CELL_PROGRESS: u32 = 1000;
+
+/// How fast snakes move, in [`CELL_PROGRESS`] per tick.
+pub struct SpeedSettings {
+    /// Speed of a snake without a tail
+    pub base: u32,
+    /// Speed gained per tail segment
+    pub per_segment: u32,
+    /// Fastest a snake can get by growing
+    pub max: u32,
+    /// Speed multiplier while boosting
+    pub boost_multiplier: u32,
+    /// Cells a boosting snake travels per tail segment it spends
+    pub boost_cells_per_segment: u32,
+    /// Speed gained while a speed pellet is in effect
+    pub pellet_bonus: u32,
+}
+
+impl Default for SpeedSettings {
+    fn default() -> Self {
+        // 5 cells per second at the start, like the original fixed 0.2s movement
+        Self {
+            base: 250,
+            per_segment: 5,
+            max: 500,
+            boost_multiplier: 2,
+            boost_cells_per_segment: 3,
+            pellet_bonus: 150,
+        }
+    }
+}
+
+impl SpeedSettings {
+    /// Snakes can only boost while they have a tail to spend.
+    pub fn is_boosting(&self, head: &SnakeHead) -> bool {
+        head.boosting && !head.tail.is_empty()
+    }
+
+    pub fn speed(&self, head: &SnakeHead) -> u32 {
+        let mut speed = (self.base + self.per_segment * head.tail.len() as u32).min(self.max);
+        if head.speed_ticks > 0 {
+            speed += self.pellet_bonus;
+        }
+        if self.is_boosting(head) {
+            speed *= self.boost_multiplier;
+        }
+        // No faster than a cell per tick, since snakes move at most once a tick
+        speed.min(CELL_PROGRESS)
+    }
+
+    /// Whether `head` will reach the next cell on the coming tick.
+    pub fn moves_next_tick(&self, head: &SnakeHead) -> bool {
+        head.progress + self.speed(head) >= CELL_PROGRESS
+    }
+}
+
+/// Keys the local player steers and boosts with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub left: KeyCode,
+    pub up: KeyCode,
+    pub right: KeyCode,
+    pub down: KeyCode,
+    pub boost: KeyCode,
+}
+
+impl KeyBindings {
+    /// Key that turns the snake towards `direction`.
+    pub fn turn(&self, direction: Direction) -> KeyCode {
+        match direction {
+            Direction::Left => self.left,
+            Direction::Up => self.up,
+            Direction::Right => self.right,
+            Direction::Down => self.down,
+        }
+    }
+
+    pub fn turn_mut(&mut self, direction: Direction) -> &mut KeyCode {
+        match direction {
+            Direction::Left => &mut self.left,
+            Direction::Up => &mut self.up,
+            Direction::Right => &mut self.right,
+            Direction::Down => &mut self.down,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::Left,
+            up: KeyCode::Up,
+            right: KeyCode::Right,
+            down: KeyCode::Down,
+            boost: KeyCode::Space,
+        }
+    }
+}
@@ -2,6 +2,20 @@
 pub enum GameState {
     MainMenu,
     Paused,
+    /// Counting down to the start of a round, with the snakes in place but frozen
     PreGame,
     Running,
+    /// Scoreboard between the rounds of a match
+    Intermission,
+    /// Final standings once a match is over
+    Results,
+}
+
+/// Screen of the main menu being shown, while in [`GameState::MainMenu`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MenuState {
+    Main,
+    Settings,
+    /// Joining a server, typed in or picked from the recent and LAN ones
+    Multiplayer,
 }
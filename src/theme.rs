@@ -0,0 +1,139 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::common::components::{Direction, Position, Size};
+use crate::food::components::Food;
+use crate::snake::components::{SnakeHead, Tail};
+use crate::state::GameState;
+use crate::theme::components::{
+    SnakeAtlas, Theme, ATLAS_COLUMNS, ATLAS_PATH, ATLAS_ROWS, ATLAS_TILE, CORNER, END, FOOD, FOOD_FPS, FOOD_FRAMES,
+    HEAD, STRAIGHT,
+};
+
+pub mod components;
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Theme>().add_startup_system(load_atlas).add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            ConditionSet::new()
+                .run_not_in_state(GameState::MainMenu)
+                .run_if(|theme: Res<Theme>| *theme == Theme::Atlas)
+                .with_system(use_atlas)
+                .with_system(orient_snakes)
+                .with_system(animate_food)
+                .into(),
+        );
+    }
+}
+
+fn load_atlas(mut commands: Commands, asset_server: Res<AssetServer>, mut atlases: ResMut<Assets<TextureAtlas>>) {
+    let image = asset_server.load(ATLAS_PATH);
+    let atlas = atlases.add(TextureAtlas::from_grid(
+        image.clone(),
+        Vec2::splat(ATLAS_TILE),
+        ATLAS_COLUMNS,
+        ATLAS_ROWS,
+    ));
+    commands.insert_resource(SnakeAtlas { image, atlas });
+}
+
+/// Swaps the flat square of everything on the board for a sprite from the atlas, once it has loaded. Falls
+/// back to the flat theme if the atlas can't be loaded.
+fn use_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    atlas: Res<SnakeAtlas>,
+    sprites: Query<(Entity, &Sprite, Option<&Food>), With<Size>>,
+) {
+    match asset_server.get_load_state(&atlas.image) {
+        LoadState::Loaded => {}
+        LoadState::Failed => {
+            warn!("Couldn't load {}, falling back to the flat theme", ATLAS_PATH);
+            commands.insert_resource(Theme::Flat);
+            return;
+        }
+        _ => return,
+    }
+
+    for (entity, sprite, food) in sprites.iter() {
+        commands
+            .entity(entity)
+            .remove::<Sprite>()
+            .remove::<Handle<Image>>()
+            .insert(TextureAtlasSprite {
+                color: sprite.color,
+                index: if food.is_some() { FOOD } else { END },
+                // Scaled to the size of a cell by `size_scaling`, just like the flat squares
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            })
+            .insert(atlas.atlas.clone());
+    }
+}
+
+/// Rotation of a sprite drawn facing up so that it faces `direction`.
+fn rotation(direction: Direction) -> Quat {
+    Quat::from_rotation_z(match direction {
+        Direction::Up => 0.0,
+        Direction::Left => FRAC_PI_2,
+        Direction::Down => PI,
+        Direction::Right => -FRAC_PI_2,
+    })
+}
+
+/// Turns heads the way they're going, and picks the straight, corner or end sprite for each tail segment from
+/// the cells either side of it.
+fn orient_snakes(
+    mut heads: Query<(&Position, &SnakeHead, &mut Transform, &mut TextureAtlasSprite), Without<Tail>>,
+    mut tails: Query<(&Position, &mut Transform, &mut TextureAtlasSprite), With<Tail>>,
+) {
+    for (head_position, head, mut transform, mut sprite) in heads.iter_mut() {
+        sprite.index = HEAD;
+        transform.rotation = rotation(head.direction);
+
+        let positions: Vec<Option<Position>> =
+            head.tail.iter().map(|tail| tails.get(*tail).ok().map(|(position, _, _)| *position)).collect();
+        for (i, tail) in head.tail.iter().enumerate() {
+            let (position, mut transform, mut sprite) = match tails.get_mut(*tail) {
+                Ok(tail) => tail,
+                Err(_) => continue,
+            };
+            let previous = if i == 0 { Some(*head_position) } else { positions[i - 1] };
+            let next = positions.get(i + 1).copied().flatten();
+            // Segments that haven't unfolded yet sit on the same cell as their neighbours
+            let forward = previous.and_then(|previous| position.direction_to(previous)).unwrap_or(head.direction);
+            let back = next.and_then(|next| position.direction_to(next));
+
+            let (index, turns) = match back {
+                Some(back) if back == forward.opposite() => (STRAIGHT, forward),
+                Some(back) if back != forward => {
+                    // The corner sprite joins down and right, find the quarter turns that join these two sides
+                    let mut joins = (Direction::Down, Direction::Right);
+                    let mut turns = Direction::Up;
+                    while !((joins.0 == forward && joins.1 == back) || (joins.0 == back && joins.1 == forward)) {
+                        joins = (joins.0.turn_left(), joins.1.turn_left());
+                        turns = turns.turn_left();
+                    }
+                    (CORNER, turns)
+                }
+                // Either the tip of the tail, or doubled back on itself through a ghost
+                _ => (END, forward),
+            };
+            sprite.index = index;
+            transform.rotation = rotation(turns);
+        }
+    }
+}
+
+fn animate_food(time: Res<Time>, mut sprites: Query<&mut TextureAtlasSprite, With<Food>>) {
+    let frame = (time.seconds_since_startup() * FOOD_FPS) as usize % FOOD_FRAMES;
+    for mut sprite in sprites.iter_mut() {
+        sprite.index = FOOD + frame;
+    }
+}
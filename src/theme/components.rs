@@ -0,0 +1,62 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::{Handle, Image, TextureAtlas};
+
+/// How the board is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Plain colored squares, which need no assets
+    Flat,
+    /// Sprites from [`ATLAS_PATH`], with heads facing where they're going and tails bending round corners
+    #[default]
+    Atlas,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Flat, Theme::Atlas];
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Flat => "flat",
+            Theme::Atlas => "atlas",
+        })
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown theme '{}', expected one of flat, atlas", s))
+    }
+}
+
+/// Grid of white sprites, tinted with the color of whatever they're drawn for.
+pub const ATLAS_PATH: &str = "textures/snake_atlas.png";
+/// Pixels per side of a sprite in the atlas
+pub const ATLAS_TILE: f32 = 16.0;
+pub const ATLAS_COLUMNS: usize = 4;
+pub const ATLAS_ROWS: usize = 2;
+
+// Sprite indices in the atlas. Sprites are drawn for a snake heading up the screen: the head faces the top,
+// straight segments join top and bottom, corners join bottom and right, and the tail end joins the top.
+pub const HEAD: usize = 0;
+pub const STRAIGHT: usize = 1;
+pub const CORNER: usize = 2;
+pub const END: usize = 3;
+/// First of the food animation's frames
+pub const FOOD: usize = 4;
+pub const FOOD_FRAMES: usize = 4;
+/// Food animation frames per second
+pub const FOOD_FPS: f64 = 6.0;
+
+pub struct SnakeAtlas {
+    pub image: Handle<Image>,
+    pub atlas: Handle<TextureAtlas>,
+}
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::ai::brain::{SnakeBrain, Strategy};
+use crate::server::bot_api::spawn_process_bot;
+use crate::simulation::Simulation;
+use crate::snake::components::SpawnSettings;
+use crate::snake::spawn_points;
+
+const INITIAL_RATING: f64 = 1000.0;
+const ELO_K: f64 = 32.0;
+
+/// Runs headless matches between bots and ranks them.
+///
+/// Round `n` of a tournament is played with seed `seed + n`, so any single match can be replayed with
+/// `--rounds 1 --seed <match seed>` and the same bots.
+#[derive(Args, Debug)]
+pub struct TournamentArgs {
+    /// Bot taking part, either a built-in strategy (random, greedy, floodfill) or `exec:<command>` to run an
+    /// external bot process speaking the bot protocol over stdin/stdout. Repeat for each bot.
+    #[arg(long = "bot", required = true)]
+    pub bots: Vec<BotSpec>,
+
+    /// Number of bots in each match. Every combination of this many bots plays once per round
+    #[arg(long, default_value_t = 2)]
+    pub players: usize,
+
+    /// Number of rounds to play
+    #[arg(long, default_value_t = 10)]
+    pub rounds: u64,
+
+    /// Seed of the first round
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Ticks after which a match ends if more than one snake is still alive
+    #[arg(long, default_value_t = 4000)]
+    pub max_ticks: u64,
+
+    /// How long to wait for an external bot's reply each tick
+    #[arg(long, default_value_t = 50)]
+    pub bot_deadline_ms: u64,
+
+    /// Writes one row per snake per match to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+
+    /// Writes all match results and the leaderboard to this JSON file
+    #[arg(long)]
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub enum BotSpec {
+    Builtin(Strategy),
+    Process(String),
+}
+
+impl BotSpec {
+    fn brain(&self, seed: u64, deadline: Duration) -> std::io::Result<Box<dyn SnakeBrain>> {
+        match self {
+            BotSpec::Builtin(strategy) => Ok(strategy.build(seed)),
+            BotSpec::Process(command) => Ok(Box::new(spawn_process_bot(command, deadline)?)),
+        }
+    }
+}
+
+impl fmt::Display for BotSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotSpec::Builtin(strategy) => write!(f, "{}", strategy),
+            BotSpec::Process(command) => write!(f, "exec:{}", command),
+        }
+    }
+}
+
+impl FromStr for BotSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("exec:") {
+            Some(command) => Ok(BotSpec::Process(command.to_string())),
+            None => Ok(BotSpec::Builtin(s.parse()?)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SnakeResult {
+    pub bot: String,
+    /// 1 for the winner; snakes that tie share a placement
+    pub placement: usize,
+    pub length: usize,
+    pub survival_ticks: u64,
+}
+
+#[derive(Serialize)]
+pub struct MatchResult {
+    pub seed: u64,
+    pub winner: Option<String>,
+    pub ticks: u64,
+    pub snakes: Vec<SnakeResult>,
+}
+
+#[derive(Serialize)]
+pub struct Standing {
+    pub bot: String,
+    pub rating: f64,
+    pub matches: usize,
+    pub wins: usize,
+    pub average_length: f64,
+    pub average_survival_ticks: f64,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    matches: &'a [MatchResult],
+    leaderboard: &'a [Standing],
+}
+
+pub fn run(args: &TournamentArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.players == 0 || args.players > args.bots.len() {
+        return Err(format!(
+            "--players must be between 1 and the number of bots ({})",
+            args.bots.len()
+        )
+        .into());
+    }
+    let names = bot_names(&args.bots);
+    let deadline = Duration::from_millis(args.bot_deadline_ms);
+    // Bot processes are talked to on a runtime of the tournament's own, as there's no game to own one
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _runtime = runtime.enter();
+
+    let mut results = vec![];
+    for round in 0..args.rounds {
+        let seed = args.seed + round;
+        for entrants in combinations(args.bots.len(), args.players) {
+            let result = play_match(args, &names, &entrants, seed, deadline)?;
+            println!(
+                "[tournament] seed={} {} -> winner: {}",
+                seed,
+                entrants.iter().map(|i| names[*i].as_str()).collect::<Vec<_>>().join(" vs "),
+                result.winner.as_deref().unwrap_or("draw")
+            );
+            results.push(result);
+        }
+    }
+
+    let leaderboard = leaderboard(&names, &results);
+    print_leaderboard(&leaderboard);
+    if let Some(path) = &args.csv {
+        fs::write(path, to_csv(&results))?;
+    }
+    if let Some(path) = &args.json {
+        let report = Report {
+            matches: &results,
+            leaderboard: &leaderboard,
+        };
+        fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(())
+}
+
+/// Display names for each bot, numbering any that are configured more than once.
+fn bot_names(bots: &[BotSpec]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    bots.iter()
+        .map(|bot| {
+            let name = bot.to_string();
+            let count = seen.entry(name.clone()).or_default();
+            *count += 1;
+            if *count == 1 {
+                name
+            } else {
+                format!("{}#{}", name, count)
+            }
+        })
+        .collect()
+}
+
+/// All ways of picking `k` of `n` indices, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    fn pick(start: usize, n: usize, k: usize, current: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            pick(i + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+    let mut out = vec![];
+    pick(0, n, k, &mut vec![], &mut out);
+    out
+}
+
+fn play_match(
+    args: &TournamentArgs,
+    names: &[String],
+    entrants: &[usize],
+    seed: u64,
+    deadline: Duration,
+) -> std::io::Result<MatchResult> {
+    let mut sim = Simulation::new(seed);
+    let mut snakes = vec![];
+    for (slot, (bot, (position, direction))) in
+        entrants.iter().zip(spawn_points(entrants.len(), &SpawnSettings::default())).enumerate()
+    {
+        let brain = args.bots[*bot].brain(seed.wrapping_mul(31).wrapping_add(slot as u64), deadline)?;
+        snakes.push(sim.spawn_snake(position, direction, brain));
+    }
+
+    let mut lengths: Vec<usize> = snakes.iter().map(|snake| sim.snake_length(*snake).unwrap()).collect();
+    let mut deaths: Vec<Option<u64>> = vec![None; snakes.len()];
+    // A lone snake plays until it dies, otherwise the match ends once one snake is left
+    let last_standing = if snakes.len() > 1 { 1 } else { 0 };
+    while sim.tick < args.max_ticks && deaths.iter().filter(|d| d.is_none()).count() > last_standing {
+        sim.step();
+        for (i, snake) in snakes.iter().enumerate() {
+            match sim.snake_length(*snake) {
+                Some(length) => lengths[i] = length,
+                None => {
+                    deaths[i].get_or_insert(sim.tick);
+                }
+            }
+        }
+    }
+
+    let scores: Vec<(u64, usize)> = deaths.iter().map(|d| d.unwrap_or(sim.tick)).zip(lengths.iter().copied()).collect();
+    let snakes: Vec<SnakeResult> = entrants
+        .iter()
+        .enumerate()
+        .map(|(i, bot)| SnakeResult {
+            bot: names[*bot].clone(),
+            placement: 1 + scores.iter().filter(|other| **other > scores[i]).count(),
+            length: scores[i].1,
+            survival_ticks: scores[i].0,
+        })
+        .collect();
+    let winners: Vec<&SnakeResult> = snakes.iter().filter(|s| s.placement == 1).collect();
+    Ok(MatchResult {
+        seed,
+        winner: if winners.len() == 1 { Some(winners[0].bot.clone()) } else { None },
+        ticks: sim.tick,
+        snakes,
+    })
+}
+
+/// Elo ratings from treating every match as a set of pairwise games between its snakes.
+fn leaderboard(names: &[String], results: &[MatchResult]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = names
+        .iter()
+        .map(|name| Standing {
+            bot: name.clone(),
+            rating: INITIAL_RATING,
+            matches: 0,
+            wins: 0,
+            average_length: 0.0,
+            average_survival_ticks: 0.0,
+        })
+        .collect();
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+
+    for result in results {
+        let mut deltas = vec![0.0; standings.len()];
+        for a in &result.snakes {
+            for b in &result.snakes {
+                if a.bot == b.bot {
+                    continue;
+                }
+                let (ra, rb) = (
+                    standings[index[a.bot.as_str()]].rating,
+                    standings[index[b.bot.as_str()]].rating,
+                );
+                let expected = 1.0 / (1.0 + 10f64.powf((rb - ra) / 400.0));
+                let score = match a.placement.cmp(&b.placement) {
+                    std::cmp::Ordering::Less => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 0.0,
+                };
+                deltas[index[a.bot.as_str()]] += ELO_K * (score - expected);
+            }
+        }
+
+        for snake in &result.snakes {
+            let standing = &mut standings[index[snake.bot.as_str()]];
+            standing.rating += deltas[index[snake.bot.as_str()]];
+            standing.matches += 1;
+            if result.winner.as_deref() == Some(snake.bot.as_str()) {
+                standing.wins += 1;
+            }
+            standing.average_length += snake.length as f64;
+            standing.average_survival_ticks += snake.survival_ticks as f64;
+        }
+    }
+
+    for standing in &mut standings {
+        if standing.matches > 0 {
+            standing.average_length /= standing.matches as f64;
+            standing.average_survival_ticks /= standing.matches as f64;
+        }
+    }
+    standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    standings
+}
+
+fn print_leaderboard(standings: &[Standing]) {
+    println!();
+    println!(
+        "{:<4} {:<30} {:>7} {:>7} {:>6} {:>10} {:>13}",
+        "#", "bot", "rating", "matches", "wins", "avg length", "avg survival"
+    );
+    for (i, s) in standings.iter().enumerate() {
+        println!(
+            "{:<4} {:<30} {:>7.0} {:>7} {:>6} {:>10.1} {:>13.1}",
+            i + 1,
+            s.bot,
+            s.rating,
+            s.matches,
+            s.wins,
+            s.average_length,
+            s.average_survival_ticks
+        );
+    }
+}
+
+fn to_csv(results: &[MatchResult]) -> String {
+    let mut csv = String::from("match,seed,bot,placement,length,survival_ticks,winner\n");
+    for (i, result) in results.iter().enumerate() {
+        for snake in &result.snakes {
+            csv += &format!(
+                "{},{},\"{}\",{},{},{},{}\n",
+                i,
+                result.seed,
+                snake.bot.replace('"', "\"\""),
+                snake.placement,
+                snake.length,
+                snake.survival_ticks,
+                result.winner.as_deref() == Some(snake.bot.as_str())
+            );
+        }
+    }
+    csv
+}
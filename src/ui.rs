@@ -1,18 +1,39 @@
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
-use crate::state::GameState;
+use crate::state::{GameState, MenuState};
 use crate::ui::components::*;
+use crate::ui::fps::*;
+use crate::ui::hud::*;
 use crate::ui::mainmenu::*;
+use crate::ui::multiplayer::*;
+use crate::ui::network::*;
+use crate::ui::round::*;
+use crate::ui::settings::*;
 
 mod components;
+mod fps;
+mod hud;
 mod mainmenu;
+mod multiplayer;
+mod network;
+mod round;
+mod settings;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loopless_state(GameState::MainMenu)
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_loopless_state(GameState::MainMenu)
+            .add_loopless_state(MenuState::Main)
+            .init_resource::<Editing>()
+            .init_resource::<AddressField>()
+            .add_startup_system(fps_setup)
+            .add_system(fps_text)
+            .add_startup_system(network_status_setup)
+            .add_system(network_status_text)
             .add_enter_system(GameState::MainMenu, main_menu_setup)
             // Common systems to all screens that handles buttons behaviour
             .add_system_set(
@@ -20,8 +41,58 @@ impl Plugin for UiPlugin {
                     .run_in_state(GameState::MainMenu)
                     .with_system(menu_action)
                     .with_system(button_system)
+                    .with_system(bot_settings_text)
+                    .with_system(mode_settings_text)
+                    .with_system(identity_settings_text)
                     .into(),
             )
-            .add_exit_system(GameState::MainMenu, despawn_screen::<OnMainMenuScreen>);
+            .add_exit_system(GameState::MainMenu, despawn_screen::<OnMainMenuScreen>)
+            // The settings and multiplayer screens take the main menu's place until they're closed
+            .add_enter_system(MenuState::Settings, despawn_screen::<OnMainMenuScreen>)
+            .add_enter_system(MenuState::Settings, settings_setup)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::MainMenu)
+                    .run_in_state(MenuState::Settings)
+                    .with_system(settings_action)
+                    .with_system(settings_input)
+                    .with_system(settings_text)
+                    .into(),
+            )
+            .add_exit_system(MenuState::Settings, save_settings)
+            .add_exit_system(MenuState::Settings, despawn_screen::<OnSettingsScreen>)
+            .add_exit_system(MenuState::Settings, main_menu_setup)
+            .add_enter_system(MenuState::Multiplayer, despawn_screen::<OnMainMenuScreen>)
+            .add_enter_system(MenuState::Multiplayer, multiplayer_setup)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::MainMenu)
+                    .run_in_state(MenuState::Multiplayer)
+                    .with_system(multiplayer_action)
+                    .with_system(address_input)
+                    .with_system(address_text)
+                    .with_system(server_lists)
+                    .with_system(remote_player_list)
+                    .into(),
+            )
+            .add_exit_system(MenuState::Multiplayer, despawn_screen::<OnMultiplayerScreen>)
+            .add_exit_system(MenuState::Multiplayer, main_menu_setup)
+            .add_enter_system(GameState::PreGame, countdown_setup)
+            .add_system(countdown_text.run_in_state(GameState::PreGame))
+            .add_exit_system(GameState::PreGame, despawn_screen::<OnCountdownScreen>)
+            .add_enter_system(GameState::Running, hud_setup)
+            .add_system(hud_text.run_in_state(GameState::Running))
+            .add_exit_system(GameState::Running, despawn_screen::<OnHudScreen>)
+            .add_enter_system(GameState::Intermission, intermission_setup)
+            .add_exit_system(GameState::Intermission, despawn_screen::<OnIntermissionScreen>)
+            .add_enter_system(GameState::Results, results_setup)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Results)
+                    .with_system(menu_action)
+                    .with_system(button_system)
+                    .into(),
+            )
+            .add_exit_system(GameState::Results, despawn_screen::<OnResultsScreen>);
     }
 }
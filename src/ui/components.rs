@@ -1,9 +1,27 @@
 use bevy::prelude::Component;
 
+use crate::common::components::Direction;
+
 // All actions that can be triggered from a button click
 #[derive(Component)]
 pub enum MenuButtonAction {
     NewGame,
+    CycleBots,
+    CycleBotStrategy,
+    CycleMode,
+    CycleColor,
+    CycleSkin,
+    Multiplayer,
+    Settings,
+    ChangeSetting(Setting),
+    EditAddress,
+    Connect,
+    JoinServer(String),
+    Disconnect,
+    // Kick the client in the slot off this game's server
+    Kick(usize),
+    // Back to the main menu from one of its screens
+    BackToMenu,
     BackToMainMenu,
     Quit,
 }
@@ -11,3 +29,93 @@ pub enum MenuButtonAction {
 // Tag component used to tag entities added on the main menu screen
 #[derive(Component)]
 pub struct OnMainMenuScreen;
+
+// Tag component for the text showing the current bot settings
+#[derive(Component)]
+pub enum BotSettingText {
+    Count,
+    Strategy,
+}
+
+// Tag component for the text showing the local player's look
+#[derive(Component)]
+pub enum IdentitySettingText {
+    Color,
+    Skin,
+}
+
+// Tag component for the text showing the current game mode
+#[derive(Component)]
+pub struct ModeSettingText;
+
+// Tag component used to tag entities added on the settings screen
+#[derive(Component)]
+pub struct OnSettingsScreen;
+
+// Each of the user's preferences on the settings screen, tagging the text showing it
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    WindowSize,
+    Fullscreen,
+    Turn(Direction),
+    Boost,
+    Name,
+    Color,
+    Server,
+    ShowFps,
+}
+
+// Tag component used to tag entities added on the multiplayer screen
+#[derive(Component)]
+pub struct OnMultiplayerScreen;
+
+// Tag component for the text showing the typed in server address
+#[derive(Component)]
+pub struct AddressText;
+
+// Tag component for the node listing the clients playing on this game's server
+#[derive(Component)]
+pub struct RemotePlayerList;
+
+// Tag component for the nodes listing servers, which are filled in as servers are found
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ServerList {
+    Recent,
+    Lan,
+}
+
+// Tag component for the text describing a listed server, by the address it's joined on
+#[derive(Component)]
+pub struct ServerEntryText(pub String);
+
+// Tag component for the frames per second counter
+#[derive(Component)]
+pub struct FpsText;
+
+// Tag component for the text showing the latest news of the network on every screen
+#[derive(Component)]
+pub struct NetworkStatusText;
+
+// Tag component used to tag entities added for the countdown before a round
+#[derive(Component)]
+pub struct OnCountdownScreen;
+
+// Tag component for the text showing the seconds left of the countdown
+#[derive(Component)]
+pub struct CountdownText;
+
+// Tag component used to tag entities added for the in-game HUD
+#[derive(Component)]
+pub struct OnHudScreen;
+
+// Tag component for the text listing the snakes in the game
+#[derive(Component)]
+pub struct HudText;
+
+// Tag component used to tag entities added on the scoreboard between rounds
+#[derive(Component)]
+pub struct OnIntermissionScreen;
+
+// Tag component used to tag entities added on the results screen
+#[derive(Component)]
+pub struct OnResultsScreen;
@@ -0,0 +1,44 @@
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use crate::config::UserConfig;
+use crate::ui::components::FpsText;
+
+pub fn fps_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(FpsText);
+}
+
+// Shows the frame rate in the corner on every screen, if turned on in the settings
+pub fn fps_text(
+    diagnostics: Res<Diagnostics>,
+    config: Res<UserConfig>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<FpsText>>,
+) {
+    let fps = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS).and_then(|fps| fps.average());
+    for (mut text, mut visibility) in &mut texts {
+        visibility.is_visible = config.show_fps;
+        if let Some(fps) = fps {
+            text.sections[0].value = format!("{:.0} FPS", fps);
+        }
+    }
+}
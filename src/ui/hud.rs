@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+
+use crate::player::components::Identity;
+use crate::snake::components::SnakeHead;
+use crate::ui::components::{HudText, OnHudScreen};
+
+pub fn hud_setup(mut commands: Commands) {
+    commands
+        .spawn_bundle(TextBundle::from_sections([]).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }))
+        .insert(OnHudScreen)
+        .insert(HudText);
+}
+
+// Lists every snake in the game by name and length, longest first, in the snake's own color
+pub fn hud_text(
+    asset_server: Res<AssetServer>,
+    heads: Query<(&SnakeHead, &Identity)>,
+    mut texts: Query<&mut Text, With<HudText>>,
+) {
+    let mut snakes: Vec<(&str, usize, Color)> =
+        heads.iter().map(|(head, identity)| (identity.name.as_str(), head.tail.len() + 1, identity.color)).collect();
+    snakes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    for mut text in &mut texts {
+        text.sections = snakes
+            .iter()
+            .map(|(name, length, color)| {
+                TextSection::new(
+                    format!("{}  {}\n", name, length),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 24.0,
+                        color: *color,
+                    },
+                )
+            })
+            .collect();
+    }
+}
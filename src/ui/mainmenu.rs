@@ -1,20 +1,30 @@
-use crate::state::GameState;
-use crate::ui::components::{MenuButtonAction, OnMainMenuScreen};
+use crate::ai::components::BotSettings;
+use crate::ai::MAX_BOTS;
+use crate::mode::components::ModeSettings;
+use crate::player::components::{LocalIdentity, PALETTE};
+use crate::state::{GameState, MenuState};
+use crate::ui::components::{BotSettingText, IdentitySettingText, MenuButtonAction, ModeSettingText, OnMainMenuScreen};
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
-const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+pub const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
 
-pub fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn main_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bots: Res<BotSettings>,
+    mode: Res<ModeSettings>,
+    identity: Res<LocalIdentity>,
+) {
     let default_font = asset_server.load("fonts/FiraSans-Bold.ttf");
     // Common style for all buttons on the screen
     let button_style = Style {
-        size: Size::new(Val::Px(250.0), Val::Px(65.0)),
-        margin: UiRect::all(Val::Px(20.0)),
+        size: Size::new(Val::Px(250.0), Val::Px(56.0)),
+        margin: UiRect::all(Val::Px(12.0)),
         justify_content: JustifyContent::Center,
         align_items: AlignItems::Center,
         ..default()
@@ -65,6 +75,108 @@ pub fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                     parent.spawn_bundle(TextBundle::from_section("New Game", button_text_style.clone()));
                 });
 
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::Multiplayer)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Multiplayer", button_text_style.clone()));
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::CycleMode)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(mode_label(&mode), button_text_style.clone()))
+                        .insert(ModeSettingText);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::CycleColor)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            "Color",
+                            TextStyle {
+                                color: identity.0.color,
+                                ..button_text_style.clone()
+                            },
+                        ))
+                        .insert(IdentitySettingText::Color);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::CycleSkin)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            skin_label(&identity),
+                            button_text_style.clone(),
+                        ))
+                        .insert(IdentitySettingText::Skin);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::CycleBots)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            bot_count_label(&bots),
+                            button_text_style.clone(),
+                        ))
+                        .insert(BotSettingText::Count);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::CycleBotStrategy)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            bot_strategy_label(&bots),
+                            button_text_style.clone(),
+                        ))
+                        .insert(BotSettingText::Strategy);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::Settings)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Settings", button_text_style.clone()));
+                });
+
             parent
                 .spawn_bundle(ButtonBundle {
                     style: button_style.clone(),
@@ -93,11 +205,32 @@ pub fn menu_action(
     mut commands: Commands,
     interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut bots: ResMut<BotSettings>,
+    mut mode: ResMut<ModeSettings>,
+    mut identity: ResMut<LocalIdentity>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Clicked {
             match menu_button_action {
                 MenuButtonAction::NewGame => commands.insert_resource(NextState(GameState::PreGame)),
+                MenuButtonAction::CycleBots => bots.count = (bots.count + 1) % (MAX_BOTS + 1),
+                MenuButtonAction::CycleBotStrategy => bots.strategy = bots.strategy.next(),
+                MenuButtonAction::CycleMode => mode.mode = mode.mode.next(),
+                MenuButtonAction::CycleColor => {
+                    let i = PALETTE.iter().position(|color| *color == identity.0.color).map_or(0, |i| i + 1);
+                    identity.0.color = PALETTE[i % PALETTE.len()];
+                }
+                MenuButtonAction::CycleSkin => identity.0.skin = identity.0.skin.next(),
+                MenuButtonAction::Multiplayer => commands.insert_resource(NextState(MenuState::Multiplayer)),
+                MenuButtonAction::Settings => commands.insert_resource(NextState(MenuState::Settings)),
+                MenuButtonAction::BackToMenu => commands.insert_resource(NextState(MenuState::Main)),
+                // Handled by the settings and multiplayer screens' own systems
+                MenuButtonAction::ChangeSetting(_)
+                | MenuButtonAction::EditAddress
+                | MenuButtonAction::Connect
+                | MenuButtonAction::JoinServer(_)
+                | MenuButtonAction::Disconnect
+                | MenuButtonAction::Kick(_) => {}
                 MenuButtonAction::BackToMainMenu => commands.insert_resource(NextState(GameState::MainMenu)),
                 MenuButtonAction::Quit => app_exit_events.send(AppExit),
             }
@@ -105,6 +238,55 @@ pub fn menu_action(
     }
 }
 
+// Keeps the bot buttons' labels in sync with the current bot settings
+pub fn bot_settings_text(bots: Res<BotSettings>, mut texts: Query<(&mut Text, &BotSettingText)>) {
+    if bots.is_changed() {
+        for (mut text, setting) in &mut texts {
+            text.sections[0].value = match setting {
+                BotSettingText::Count => bot_count_label(&bots),
+                BotSettingText::Strategy => bot_strategy_label(&bots),
+            };
+        }
+    }
+}
+
+// Keeps the mode button's label in sync with the chosen mode
+pub fn mode_settings_text(mode: Res<ModeSettings>, mut texts: Query<&mut Text, With<ModeSettingText>>) {
+    if mode.is_changed() {
+        for mut text in &mut texts {
+            text.sections[0].value = mode_label(&mode);
+        }
+    }
+}
+
+// Keeps the color and skin buttons in sync with the local player's choice
+pub fn identity_settings_text(identity: Res<LocalIdentity>, mut texts: Query<(&mut Text, &IdentitySettingText)>) {
+    if identity.is_changed() {
+        for (mut text, setting) in &mut texts {
+            match setting {
+                IdentitySettingText::Color => text.sections[0].style.color = identity.0.color,
+                IdentitySettingText::Skin => text.sections[0].value = skin_label(&identity),
+            }
+        }
+    }
+}
+
+fn skin_label(identity: &LocalIdentity) -> String {
+    format!("Skin: {}", identity.0.skin)
+}
+
+fn mode_label(mode: &ModeSettings) -> String {
+    format!("Mode: {}", mode.mode)
+}
+
+fn bot_count_label(bots: &BotSettings) -> String {
+    format!("Bots: {}", bots.count)
+}
+
+fn bot_strategy_label(bots: &BotSettings) -> String {
+    format!("AI: {}", bots.strategy)
+}
+
 // Generic system that takes a component as a parameter, and will despawn all entities with that component
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
@@ -0,0 +1,376 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::ReceivedCharacter;
+
+use crate::client::browser::ServerBrowser;
+use crate::config::UserConfig;
+use crate::net::NetRuntime;
+use crate::player::components::LocalIdentity;
+use crate::server::server::{ServerHandle, ServerStatus};
+use crate::ui::components::{
+    AddressText, MenuButtonAction, OnMultiplayerScreen, RemotePlayerList, ServerEntryText, ServerList,
+};
+use crate::ui::mainmenu::{NORMAL_BUTTON, TEXT_COLOR};
+
+/// Server address typed in to connect to directly.
+#[derive(Default)]
+pub struct AddressField {
+    pub text: String,
+    pub editing: bool,
+}
+
+pub fn multiplayer_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<UserConfig>,
+    mut field: ResMut<AddressField>,
+) {
+    if field.text.is_empty() {
+        field.text = config.server.to_string();
+    }
+    let default_font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let button_style = Style {
+        size: Size::new(Val::Px(560.0), Val::Px(44.0)),
+        margin: UiRect::all(Val::Px(6.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font: default_font.clone(),
+        font_size: 28.0,
+        color: TEXT_COLOR,
+    };
+    let heading = |text: &str| {
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: default_font.clone(),
+                font_size: 24.0,
+                color: TEXT_COLOR,
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::new(Val::Px(0.0), Val::Px(0.0), Val::Px(16.0), Val::Px(4.0)),
+            ..default()
+        })
+    };
+    let list = NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::SEA_GREEN.into(),
+            ..default()
+        })
+        .insert(OnMultiplayerScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    "Multiplayer",
+                    TextStyle {
+                        font: default_font.clone(),
+                        font_size: 60.0,
+                        color: TEXT_COLOR,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::EditAddress)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            address_label(&field),
+                            button_text_style.clone(),
+                        ))
+                        .insert(AddressText);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::Connect)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Connect", button_text_style.clone()));
+                });
+
+            parent.spawn_bundle(heading("Recent servers"));
+            parent.spawn_bundle(list.clone()).insert(ServerList::Recent);
+            parent.spawn_bundle(heading("LAN servers"));
+            parent.spawn_bundle(list.clone()).insert(ServerList::Lan);
+
+            parent.spawn_bundle(heading("Players on your server"));
+            parent.spawn_bundle(list.clone()).insert(RemotePlayerList);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::Disconnect)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Disconnect", button_text_style.clone()));
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::BackToMenu)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Back", button_text_style.clone()));
+                });
+        });
+}
+
+// Joins the server typed in or clicked on, leaves it, kicks a client off this game's server, or starts typing an
+// address
+pub fn multiplayer_action(
+    interaction_query: Query<(&Interaction, &MenuButtonAction), Changed<Interaction>>,
+    runtime: Res<NetRuntime>,
+    server: Option<Res<ServerHandle>>,
+    mut config: ResMut<UserConfig>,
+    identity: Res<LocalIdentity>,
+    mut browser: ResMut<ServerBrowser>,
+    mut field: ResMut<AddressField>,
+) {
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        field.editing = false;
+        match menu_button_action {
+            MenuButtonAction::EditAddress => field.editing = true,
+            MenuButtonAction::Connect => browser.join(&field.text, &identity.0, &runtime, &mut config),
+            MenuButtonAction::JoinServer(host) => browser.join(host, &identity.0, &runtime, &mut config),
+            MenuButtonAction::Disconnect => browser.leave(),
+            MenuButtonAction::Kick(slot) => {
+                if let Some(server) = &server {
+                    server.kick(*slot);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Feeds typing into the address field. Enter connects, escape stops typing
+pub fn address_input(
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    runtime: Res<NetRuntime>,
+    mut config: ResMut<UserConfig>,
+    identity: Res<LocalIdentity>,
+    mut browser: ResMut<ServerBrowser>,
+    mut field: ResMut<AddressField>,
+) {
+    let typed: String = characters.iter().map(|event| event.char).filter(|c| !c.is_control()).collect();
+    if !field.editing {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        field.editing = false;
+    } else if keys.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]) {
+        field.editing = false;
+        browser.join(&field.text, &identity.0, &runtime, &mut config);
+    } else if keys.just_pressed(KeyCode::Back) {
+        field.text.pop();
+    } else if !typed.is_empty() {
+        field.text.push_str(&typed);
+    }
+}
+
+// Keeps the address field's label in sync with what's typed
+pub fn address_text(field: Res<AddressField>, mut texts: Query<&mut Text, With<AddressText>>) {
+    if field.is_changed() {
+        for mut text in &mut texts {
+            text.sections[0].value = address_label(&field);
+        }
+    }
+}
+
+// Fills in the server lists, only replacing their buttons when servers come and go so they can still be
+// clicked on, and keeps the servers' details and pings up to date
+pub fn server_lists(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<UserConfig>,
+    browser: Res<ServerBrowser>,
+    lists: Query<(Entity, &ServerList, Option<&Children>)>,
+    buttons: Query<&MenuButtonAction>,
+    mut texts: Query<(&mut Text, &ServerEntryText)>,
+) {
+    let mut labels: HashMap<String, String> = HashMap::new();
+    for host in &config.recent_servers {
+        labels.insert(host.clone(), format!("{}  {}", host, ping_label(&browser, host)));
+    }
+    for server in &browser.lan {
+        let host = server.addr.to_string();
+        let info = &server.info;
+        let label = if info.is_compatible() {
+            format!(
+                "{}  {} on {}  {}/{}  {}",
+                info.name,
+                info.mode,
+                info.map,
+                info.players,
+                info.max_players,
+                ping_label(&browser, &host)
+            )
+        } else {
+            format!("{}  version {}, can't join", info.name, info.version)
+        };
+        labels.insert(host, label);
+    }
+
+    for (list, kind, children) in lists.iter() {
+        let hosts: Vec<String> = match kind {
+            ServerList::Recent => config.recent_servers.clone(),
+            ServerList::Lan => browser.lan.iter().map(|server| server.addr.to_string()).collect(),
+        };
+        let listed: Vec<&String> = children
+            .into_iter()
+            .flatten()
+            .filter_map(|child| match buttons.get(*child) {
+                Ok(MenuButtonAction::JoinServer(host)) => Some(host),
+                _ => None,
+            })
+            .collect();
+        if listed.len() == hosts.len() && listed.iter().zip(&hosts).all(|(listed, host)| *listed == host) {
+            continue;
+        }
+
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            for host in hosts {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(560.0), Val::Px(36.0)),
+                            margin: UiRect::all(Val::Px(3.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(MenuButtonAction::JoinServer(host.clone()))
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle::from_section(
+                                labels.get(&host).cloned().unwrap_or_default(),
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 22.0,
+                                    color: TEXT_COLOR,
+                                },
+                            ))
+                            .insert(ServerEntryText(host));
+                    });
+            }
+        });
+    }
+
+    for (mut text, ServerEntryText(host)) in &mut texts {
+        if let Some(label) = labels.get(host) {
+            if text.sections[0].value != *label {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
+
+// Lists the clients holding a slot on this game's server, each a button to kick them
+pub fn remote_player_list(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    status: Res<ServerStatus>,
+    lists: Query<Entity, With<RemotePlayerList>>,
+    added: Query<(), Added<RemotePlayerList>>,
+) {
+    if !status.is_changed() && added.is_empty() {
+        return;
+    }
+    for list in &lists {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            for player in &status.players {
+                let label = if player.connected {
+                    format!("{} {}  kick", player.slot, player.name)
+                } else {
+                    format!("{} {}  reconnecting...  kick", player.slot, player.name)
+                };
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(560.0), Val::Px(36.0)),
+                            margin: UiRect::all(Val::Px(3.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(MenuButtonAction::Kick(player.slot))
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 22.0,
+                                color: TEXT_COLOR,
+                            },
+                        ));
+                    });
+            }
+        });
+    }
+}
+
+fn address_label(field: &AddressField) -> String {
+    if field.editing {
+        format!("Address: {}_", field.text)
+    } else {
+        format!("Address: {}", field.text)
+    }
+}
+
+fn ping_label(browser: &ServerBrowser, host: &str) -> String {
+    match browser.pings.get(host) {
+        Some(Some(rtt)) => format!("{} ms", rtt.as_millis().max(1)),
+        Some(None) => "no answer".to_string(),
+        None => "...".to_string(),
+    }
+}
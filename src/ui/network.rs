@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+
+use crate::client::browser::ServerBrowser;
+use crate::server::server::ServerStatus;
+use crate::ui::components::NetworkStatusText;
+
+pub fn network_status_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(NetworkStatusText);
+}
+
+// Shows the latest news of the server joined and of this game's own clients in the corner on every screen, so
+// dropped connections and kicks are noticed mid-game too
+pub fn network_status_text(
+    browser: Res<ServerBrowser>,
+    server: Res<ServerStatus>,
+    mut texts: Query<&mut Text, With<NetworkStatusText>>,
+) {
+    if !browser.is_changed() && !server.is_changed() {
+        return;
+    }
+    let lines: Vec<&str> = [&browser.status, &server.last_event].into_iter().flatten().map(String::as_str).collect();
+    for mut text in &mut texts {
+        text.sections[0].value = lines.join("\n");
+    }
+}
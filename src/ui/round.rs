@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use crate::mode::components::{MatchResults, MatchState, ModeSettings, ServerClock};
+use crate::ui::components::{
+    CountdownText, MenuButtonAction, OnCountdownScreen, OnIntermissionScreen, OnResultsScreen,
+};
+use crate::ui::mainmenu::{NORMAL_BUTTON, TEXT_COLOR};
+
+pub fn countdown_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(OnCountdownScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 160.0,
+                        color: TEXT_COLOR,
+                    },
+                ))
+                .insert(CountdownText);
+        });
+}
+
+// Shows the whole seconds left until the round starts, counting down 3-2-1
+pub fn countdown_text(
+    time: Res<Time>,
+    clock: Res<ServerClock>,
+    state: Res<MatchState>,
+    mut texts: Query<&mut Text, With<CountdownText>>,
+) {
+    let left = (state.phase_ends_at - clock.now(&time)).ceil().max(1.0);
+    for mut text in &mut texts {
+        text.sections[0].value = format!("{}", left as u32);
+    }
+}
+
+pub fn intermission_setup(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ModeSettings>,
+    state: Res<MatchState>,
+    results: Res<MatchResults>,
+) {
+    let title = format!("Round {} of {}", state.round, settings.rounds);
+    scoreboard(commands, &asset_server, OnIntermissionScreen, title, &results, false);
+}
+
+pub fn results_setup(commands: Commands, asset_server: Res<AssetServer>, results: Res<MatchResults>) {
+    let title = match &results.winner {
+        Some(winner) => format!("{} wins!", winner),
+        None => "Draw".to_string(),
+    };
+    scoreboard(commands, &asset_server, OnResultsScreen, title, &results, true);
+}
+
+// Lists the standings under `title`, with a button back to the main menu once the match is over
+fn scoreboard(
+    mut commands: Commands,
+    asset_server: &AssetServer,
+    screen: impl Component,
+    title: String,
+    results: &MatchResults,
+    back_button: bool,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                padding: UiRect::all(Val::Px(30.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+            ..default()
+        })
+        .insert(screen)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 80.0,
+                        color: TEXT_COLOR,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(30.0)),
+                    ..default()
+                }),
+            );
+
+            for (i, standing) in results.standings.iter().enumerate() {
+                parent.spawn_bundle(TextBundle::from_section(
+                    format!("{}. {}  {}", i + 1, standing.name, standing.score),
+                    text_style.clone(),
+                ));
+            }
+
+            if back_button {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                            margin: UiRect::all(Val::Px(30.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(MenuButtonAction::BackToMainMenu)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle::from_section("Main Menu", text_style.clone()));
+                    });
+            }
+        });
+}
@@ -0,0 +1,276 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::common::components::Direction;
+use crate::config::{UserConfig, WINDOW_SIZES};
+use crate::player::components::PALETTE;
+use crate::player::{color_hex, parse_color};
+use crate::ui::components::{MenuButtonAction, OnSettingsScreen, Setting};
+use crate::ui::mainmenu::{NORMAL_BUTTON, TEXT_COLOR};
+
+/// Settings in the order they're listed on the settings screen.
+const SETTINGS: [Setting; 11] = [
+    Setting::Name,
+    Setting::Color,
+    Setting::WindowSize,
+    Setting::Fullscreen,
+    Setting::Turn(Direction::Up),
+    Setting::Turn(Direction::Down),
+    Setting::Turn(Direction::Left),
+    Setting::Turn(Direction::Right),
+    Setting::Boost,
+    Setting::Server,
+    Setting::ShowFps,
+];
+
+/// Longest name that can be typed in, which still fits on a name tag
+const MAX_NAME_LENGTH: usize = 16;
+
+/// The setting being typed into or waiting for a key press, if any.
+#[derive(Default)]
+pub struct Editing {
+    pub setting: Option<Setting>,
+    /// Server address typed so far, only saved once it's a valid one
+    pub text: String,
+}
+
+pub fn settings_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<UserConfig>,
+    editing: Res<Editing>,
+) {
+    let default_font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    // Smaller than the main menu's buttons, so they all fit on the screen
+    let button_style = Style {
+        size: Size::new(Val::Px(420.0), Val::Px(44.0)),
+        margin: UiRect::all(Val::Px(6.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font: default_font.clone(),
+        font_size: 28.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::SEA_GREEN.into(),
+            ..default()
+        })
+        .insert(OnSettingsScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    "Settings",
+                    TextStyle {
+                        font: default_font.clone(),
+                        font_size: 60.0,
+                        color: TEXT_COLOR,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+
+            for setting in SETTINGS {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: button_style.clone(),
+                        color: NORMAL_BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(MenuButtonAction::ChangeSetting(setting))
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle::from_section(
+                                setting_label(&config, &editing, setting),
+                                TextStyle {
+                                    color: setting_color(&config, setting),
+                                    ..button_text_style.clone()
+                                },
+                            ))
+                            .insert(setting);
+                    });
+            }
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: button_style.clone(),
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButtonAction::BackToMenu)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Back", button_text_style.clone()));
+                });
+        });
+}
+
+// Changes the clicked setting, or starts editing it if it's typed in
+pub fn settings_action(
+    interaction_query: Query<(&Interaction, &MenuButtonAction), Changed<Interaction>>,
+    mut config: ResMut<UserConfig>,
+    mut editing: ResMut<Editing>,
+) {
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // Clicking anywhere else is done typing
+        finish_editing(&mut config, &mut editing);
+        if let MenuButtonAction::ChangeSetting(setting) = menu_button_action {
+            match setting {
+                Setting::WindowSize => {
+                    let size = (config.window.width, config.window.height);
+                    let i = WINDOW_SIZES.iter().position(|s| *s == size).map_or(0, |i| i + 1);
+                    (config.window.width, config.window.height) = WINDOW_SIZES[i % WINDOW_SIZES.len()];
+                }
+                Setting::Fullscreen => config.window.fullscreen = !config.window.fullscreen,
+                Setting::Color => {
+                    let color = parse_color(&config.color).ok();
+                    let i = PALETTE.iter().position(|c| Some(*c) == color).map_or(0, |i| i + 1);
+                    config.color = color_hex(PALETTE[i % PALETTE.len()]);
+                }
+                Setting::ShowFps => config.show_fps = !config.show_fps,
+                Setting::Server => {
+                    editing.setting = Some(*setting);
+                    editing.text = config.server.to_string();
+                }
+                Setting::Turn(_) | Setting::Boost | Setting::Name => editing.setting = Some(*setting),
+            }
+        }
+    }
+}
+
+pub fn save_settings(mut config: ResMut<UserConfig>, mut editing: ResMut<Editing>) {
+    finish_editing(&mut config, &mut editing);
+    if let Err(e) = config.save() {
+        warn!("Couldn't save settings: {}", e);
+    }
+}
+
+/// Takes the server address typed so far if it's a valid one, and stops editing.
+fn finish_editing(config: &mut UserConfig, editing: &mut Editing) {
+    if editing.setting == Some(Setting::Server) {
+        match editing.text.parse() {
+            Ok(server) => config.server = server,
+            Err(_) => warn!("Ignoring invalid server address '{}'", editing.text),
+        }
+    }
+    if editing.setting.is_some() {
+        editing.setting = None;
+    }
+}
+
+// Feeds typing and key presses into the setting being edited. Enter is done, escape gives up
+pub fn settings_input(
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut config: ResMut<UserConfig>,
+    mut editing: ResMut<Editing>,
+) {
+    let typed: String = characters.iter().map(|event| event.char).filter(|c| !c.is_control()).collect();
+    let setting = match editing.setting {
+        Some(setting) => setting,
+        None => return,
+    };
+    if let Setting::Turn(_) | Setting::Boost = setting {
+        if let Some(pressed) = keys.get_just_pressed().next() {
+            if *pressed != KeyCode::Escape {
+                match setting {
+                    Setting::Turn(direction) => *config.keys.turn_mut(direction) = *pressed,
+                    _ => config.keys.boost = *pressed,
+                }
+            }
+            editing.setting = None;
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editing.setting = None;
+        return;
+    }
+    if keys.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]) {
+        finish_editing(&mut config, &mut editing);
+        return;
+    }
+    // Only touched when typed into, so nothing else sees the settings change every frame
+    let backspace = keys.just_pressed(KeyCode::Back);
+    if typed.is_empty() && !backspace {
+        return;
+    }
+    let text = if setting == Setting::Name { &mut config.name } else { &mut editing.text };
+    if backspace {
+        text.pop();
+    }
+    text.push_str(&typed);
+    if setting == Setting::Name {
+        *text = text.chars().take(MAX_NAME_LENGTH).collect();
+    }
+}
+
+// Keeps the settings' labels in sync with the settings and what's being typed
+pub fn settings_text(config: Res<UserConfig>, editing: Res<Editing>, mut texts: Query<(&mut Text, &Setting)>) {
+    if config.is_changed() || editing.is_changed() {
+        for (mut text, setting) in &mut texts {
+            text.sections[0].value = setting_label(&config, &editing, *setting);
+            text.sections[0].style.color = setting_color(&config, *setting);
+        }
+    }
+}
+
+fn setting_label(config: &UserConfig, editing: &Editing, setting: Setting) -> String {
+    let editing = (editing.setting == Some(setting)).then_some(editing.text.as_str());
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    match setting {
+        Setting::WindowSize => format!("Window: {}x{}", config.window.width, config.window.height),
+        Setting::Fullscreen => format!("Fullscreen: {}", on_off(config.window.fullscreen)),
+        Setting::Turn(direction) => match editing {
+            Some(_) => format!("{}: press a key", direction_name(direction)),
+            None => format!("{}: {:?}", direction_name(direction), config.keys.turn(direction)),
+        },
+        Setting::Boost => match editing {
+            Some(_) => "Boost: press a key".to_string(),
+            None => format!("Boost: {:?}", config.keys.boost),
+        },
+        Setting::Name => match editing {
+            Some(_) => format!("Name: {}_", config.name),
+            None => format!("Name: {}", config.name),
+        },
+        Setting::Color => "Color".to_string(),
+        Setting::Server => match editing {
+            Some(text) => format!("Server: {}_", text),
+            None => format!("Server: {}", config.server),
+        },
+        Setting::ShowFps => format!("Show FPS: {}", on_off(config.show_fps)),
+    }
+}
+
+// The color setting is shown in the chosen color
+fn setting_color(config: &UserConfig, setting: Setting) -> Color {
+    match setting {
+        Setting::Color => parse_color(&config.color).unwrap_or(TEXT_COLOR),
+        _ => TEXT_COLOR,
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "Left",
+        Direction::Up => "Up",
+        Direction::Right => "Right",
+        Direction::Down => "Down",
+    }
+}